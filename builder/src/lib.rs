@@ -1,200 +1,2211 @@
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{quote, quote_spanned};
+use syn::ext::IdentExt;
+
+mod options;
+mod typestate;
+
+use options::{Errors, FieldDefault, FieldOptions, GroupKind, StructOptions};
 
 #[proc_macro_derive(Builder, attributes(builder))]
 pub fn derive(input: TokenStream) -> TokenStream {
-    let input: syn::DeriveInput = syn::parse(input).unwrap();
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
     //eprintln!("INPUT:\n{:#?}", input);
-    let struct_name = input.ident;
-    let builder_name = quote::format_ident!("{}Builder", struct_name);
-    let struct_fields = match input.data {
-        syn::Data::Struct(syn::DataStruct { fields, .. }) => fields,
-        _ => unimplemented!("Syntax error"),
-    };
-    let is_wrapped = |ty: &syn::Type, wrapper: &str| match ty {
-        syn::Type::Path(
-            syn::TypePath {
-                path: syn::Path { segments: s, .. },
-                ..
+    let mut errors = Errors::default();
+    let options = StructOptions::from_attrs(&input.attrs, &mut errors);
+    let name = &input.ident;
+    let targets = match input.data {
+        syn::Data::Struct(syn::DataStruct { ref fields, .. }) => vec![Target {
+            path: quote!(#name),
+            constructor: quote::format_ident!("builder"),
+            builder_name: match options.name {
+                Some(ref builder_name) => builder_name.clone(),
+                None => quote::format_ident!("{}Builder", name),
             },
-            ..,
-        ) if s.len() == 1 && s[0].ident == wrapper => Some(s[0].arguments.clone()),
-        _ => None,
+            fields: Field::parse_all(fields, &options, &mut errors),
+            variant: false,
+        }],
+        syn::Data::Enum(syn::DataEnum { ref variants, .. }) => {
+            if options.default {
+                errors.push(syn::Error::new_spanned(
+                    name,
+                    "`#[builder(default)]` on an enum is not supported, use it on fields instead",
+                ));
+            }
+            if options.typestate {
+                errors.push(syn::Error::new_spanned(
+                    name,
+                    "typestate builders are only supported for structs",
+                ));
+            }
+            if let Some(ref builder_name) = options.name {
+                errors.push(syn::Error::new_spanned(
+                    builder_name,
+                    "an enum gets one builder per variant, named `{Enum}{Variant}Builder`",
+                ));
+            }
+            for group in &options.groups {
+                errors.push(syn::Error::new_spanned(
+                    &group.path,
+                    "field groups are only supported for structs",
+                ));
+            }
+            variants
+                .iter()
+                .filter(|variant| matches!(variant.fields, syn::Fields::Named(_)))
+                .map(|variant| {
+                    let variant_name = &variant.ident;
+                    Target {
+                        path: quote!(#name::#variant_name),
+                        constructor: quote::format_ident!("{}_builder", snake_case(variant_name)),
+                        builder_name: quote::format_ident!("{}{}Builder", name, variant_name),
+                        fields: Field::parse_all(&variant.fields, &options, &mut errors),
+                        variant: true,
+                    }
+                })
+                .collect()
+        }
+        syn::Data::Union(syn::DataUnion { union_token, .. }) => {
+            errors.push(syn::Error::new_spanned(
+                union_token,
+                "unions are not supported",
+            ));
+            Vec::new()
+        }
+    };
+    if let (true, Some(param)) = (options.const_fn, input.generics.type_params().next()) {
+        errors.push(syn::Error::new_spanned(
+            param,
+            "`const` builders do not support type parameters",
+        ));
+    }
+    if let syn::Data::Struct(_) = input.data {
+        for group in &options.groups {
+            for name in &group.fields {
+                match targets[0].fields.iter().find(|field| field.ident == *name) {
+                    Some(field) if field.in_builder() => {}
+                    Some(_) => errors.push(syn::Error::new_spanned(
+                        name,
+                        "a skipped field is never set, so it cannot be part of a field group",
+                    )),
+                    None => errors.push(syn::Error::new_spanned(name, "no such field")),
+                }
+            }
+        }
+    }
+    if let Err(error) = errors.finish() {
+        return error.to_compile_error().into();
+    }
+    if options.typestate {
+        return typestate::expand(&input, &targets[0], &options).into();
+    }
+    let error_name = quote::format_ident!("{}BuilderError", name);
+    let info_names = InfoNames {
+        info: quote::format_ident!("{}FieldInfo", name),
+        status: quote::format_ident!("{}FieldStatus", name),
+    };
+    let builders = targets
+        .iter()
+        .map(|target| expand_builder(&input, target, &options, &error_name, &info_names));
+    let sub_builders = targets.iter().any(|target| {
+        target
+            .fields
+            .iter()
+            .any(|field| matches!(field.kind, FieldKind::SubBuilder(_)))
+    });
+    let error_enum = error_enum(&error_name, &options, sub_builders);
+    let field_info = field_info(&info_names, &options);
+    let output = quote! {
+        #(#builders)*
+        #error_enum
+        #field_info
     };
-    let is_option = |ty: &syn::Type| is_wrapped(ty, "Option");
-    let is_vec = |ty: &syn::Type| is_wrapped(ty, "Vec");
+    output.into()
+}
+
+/// Names of the types describing builder fields, `{Name}FieldInfo` and
+/// `{Name}FieldStatus`.
+struct InfoNames {
+    info: syn::Ident,
+    status: syn::Ident,
+}
 
-    let builder_fields = struct_fields
+/// What a builder builds: a struct, or one variant of an enum.
+struct Target<'a> {
+    /// `Command` or `Message::Connect`, used to construct the value.
+    path: proc_macro2::TokenStream,
+    /// The method on the derive input returning a new builder.
+    constructor: syn::Ident,
+    builder_name: syn::Ident,
+    fields: Vec<Field<'a>>,
+    /// Whether this is one variant of an enum, which can't be turned back
+    /// into a builder as the value may be another variant.
+    variant: bool,
+}
+
+fn expand_builder(
+    input: &syn::DeriveInput,
+    target: &Target,
+    options: &StructOptions,
+    error_name: &syn::Ident,
+    info_names: &InfoNames,
+) -> proc_macro2::TokenStream {
+    let struct_name = &input.ident;
+    let Target {
+        path,
+        constructor,
+        builder_name,
+        fields,
+        variant,
+    } = target;
+    let build_error = match options.error {
+        Some(ref error) => quote!(#error),
+        None => quote!(#error_name),
+    };
+    let generics = &input.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let struct_ty = quote!(#struct_name #ty_generics);
+
+    let serde_field = if options.serde {
+        Some(quote!(#[serde(skip_serializing_if = "core::option::Option::is_none")]))
+    } else {
+        None
+    };
+    let builder_fields = fields
         .iter()
-        .map(|field| (field.ident.as_ref().unwrap(), &field.ty))
-        .map(|(ident, ty)| {
-            if is_option(ty).is_some() {
-                quote! {#ident:#ty}
-            } else {
-                quote! {#ident: std::option::Option<#ty>}
+        .filter(|field| field.in_builder())
+        .map(|field| {
+            let Field {
+                ident, ty, kind, ..
+            } = field;
+            let attrs = &field.options.attrs;
+            // A sub-builder slot is spanned at the field's type, so that an
+            // immutable builder's derived Clone blames the field if its
+            // builder isn't Clone.
+            let ty = match kind {
+                FieldKind::SubBuilder(builder) => {
+                    let span = syn::spanned::Spanned::span(ty);
+                    quote_spanned!(span=> core::option::Option<#builder>)
+                }
+                _ => quote!(core::option::Option<#ty>),
+            };
+            quote! {
+                #serde_field
+                #(#[#attrs])*
+                #ident: #ty
             }
         });
-    let builder_methods = struct_fields
+    let builder_defaults: Vec<_> = fields
+        .iter()
+        .filter(|field| field.in_builder())
+        .map(|Field { ident, .. }| quote! {#ident: core::option::Option::None})
+        .collect();
+    let phantom = phantom_type(generics);
+    let phantom_field = phantom.as_ref().map(|phantom| {
+        let serde_skip = if options.serde {
+            Some(quote!(#[serde(skip)]))
+        } else {
+            None
+        };
+        quote!(#serde_skip __phantom: #phantom,)
+    });
+    let phantom_default = phantom
+        .as_ref()
+        .map(|_| quote!(__phantom: core::marker::PhantomData,));
+    let pattern = options.pattern;
+    let vis = options.vis();
+    let builder_methods = fields
         .iter()
-        .map(|field| (field.ident.as_ref().unwrap(), &field.ty, &field.attrs))
-        .map(|(ident, ty, attrs)| match is_option(ty) {
-            Some(syn::PathArguments::AngleBracketed(syn::AngleBracketedGenericArguments {
-                args,
+        .filter(|field| field.generates_setter())
+        .map(|field| {
+            let Field {
+                ident,
+                ty,
+                kind,
+                options: field_options,
                 ..
-            })) if args.len() == 1 => {
-                let unwrap_type = args.first().unwrap();
-                quote! {fn #ident(&mut self,value:#unwrap_type)->&mut Self{
-                    self.#ident=Some(value);
-                    self
-                }}
-            }
-            Some(_) => {
-                let error = syn::Error::new_spanned(ty, "Option need to wrap a single type")
-                    .to_compile_error();
-                quote! {#error}
-            }
-            None => {
-                let mut push_method_name: std::option::Option<std::result::Result<_, _>> = None;
-                for attr in attrs {
-                    if attr.path.is_ident("builder") {
-                        let attr_body = attr.parse_meta().unwrap();
-                        //eprintln!("{:#?}", attr_body);
-                        if let syn::Meta::List(syn::MetaList { nested, .. }) = attr_body.clone() {
-                            for meta in &nested {
-                                match meta {
-                                    syn::NestedMeta::Meta(syn::Meta::NameValue(
-                                        syn::MetaNameValue {
-                                            path,
-                                            lit: syn::Lit::Str(lit_str),
-                                            ..
-                                        },
-                                    )) => {
-                                        if path.is_ident("each") {
-                                            match lit_str.parse::<syn::Ident>() {
-                                                Ok(ident) => {
-                                                    push_method_name = Some(Ok(ident));
-                                                }
-                                                Err(_) => {
-                                                    let error = syn::Error::new_spanned(
-                                                        lit_str,
-                                                        "expected str",
-                                                    )
-                                                    .to_compile_error();
-                                                    push_method_name = Some(Err(quote! {#error}));
-                                                }
-                                            }
-                                        } else {
-                                            let error = syn::Error::new_spanned(
-                                                attr_body.clone(),
-                                                "expected `builder(each = \"...\")`",
-                                            )
-                                            .to_compile_error();
-                                            push_method_name = Some(Err(quote! {#error}));
-                                        }
-                                    }
-                                    _ => {
-                                        unimplemented!("unsupported attribute type");
-                                    }
-                                }
-                            }
-                        } else {
-                            //eprintln!("{:#?}", attr_body);
-                            unimplemented!("meta parse failed");
-                        }
+            } = field;
+            let conversion = field_options.conversion(options);
+            let setter_vis = field_options.setter_vis(options);
+            let setter_name = field.setter_name();
+            let with = field_options.setter.with.as_ref();
+            let docs = setter_docs(field, unset_note(field, options));
+            let name = ident.unraw();
+            match kind {
+                FieldKind::Optional(inner) => option_methods(pattern, field, options, inner),
+                FieldKind::Plain => {
+                    let signature = SetterSignature::value(ty, conversion, with);
+                    let set_method =
+                        pattern.setter_signed(&setter_vis, setter_name, signature, |this| {
+                            quote! {#this.#ident=core::option::Option::Some(value);}
+                        });
+                    quote! {
+                        #docs
+                        #set_method
                     }
                 }
-                match push_method_name {
-                    None => quote! {
-                        fn #ident(&mut self,value:#ty)->&mut Self{
-                            self.#ident=Some(value);
-                            self
+                FieldKind::Skipped => unreachable!(),
+                FieldKind::SubBuilder(builder) => {
+                    let signature = SetterSignature::value(ty, conversion, with);
+                    let set_method =
+                        pattern.setter_signed(&setter_vis, setter_name, signature, |this| {
+                            quote! {#this.#ident=core::option::Option::Some(core::convert::From::from(value));}
+                        });
+                    let mut_name = quote::format_ident!("{}_mut", setter_name);
+                    let mut_docs = format!(
+                        "The builder of `{}`, to set its fields in place. Starts out empty if \
+                         the field is unset.",
+                        name
+                    );
+                    quote! {
+                        #docs
+                        #set_method
+                        #[doc = #mut_docs]
+                        #setter_vis fn #mut_name(&mut self)->&mut #builder{
+                            self.#ident.get_or_insert_with(<#ty>::builder)
                         }
+                    }
+                }
+                FieldKind::Each(push_method_name, item, optional) => each_methods(
+                    pattern,
+                    field,
+                    options,
+                    push_method_name,
+                    item,
+                    optional.as_ref(),
+                ),
+            }
+        });
+    let members = fields
+        .iter()
+        .map(|Field { ident, member, .. }| quote!(#member:#ident));
+    // With `default_with` closures, build() works on its own copy of the
+    // builder, in which it fills in their defaults before taking it apart.
+    let default_with = default_with_order(fields).unwrap_or_default();
+    let (source, pattern_in_build) = if default_with.is_empty() {
+        (quote!(self), pattern)
+    } else {
+        (quote!(__builder), Pattern::Owned)
+    };
+    let default_with_copy = if default_with.is_empty() {
+        None
+    } else {
+        let copy = match pattern {
+            Pattern::Owned | Pattern::Const => quote!(self),
+            // Sub-builders need not be Clone, so they are left out of the
+            // copy and built from `self` instead.
+            Pattern::Mutable | Pattern::Immutable => {
+                let copied_fields = fields.iter().filter(|field| field.in_builder()).map(
+                    |Field { ident, kind, .. }| match kind {
+                        FieldKind::SubBuilder(_) => quote!(#ident:core::option::Option::None),
+                        _ => quote!(#ident:core::clone::Clone::clone(&self.#ident)),
                     },
-                    Some(Ok(ref push_method_name)) => match is_vec(ty) {
-                        Some(syn::PathArguments::AngleBracketed(
-                            syn::AngleBracketedGenericArguments { args, .. },
-                        )) if args.len() == 1 => {
-                            let unwrap_type = args.first().unwrap();
-                            if push_method_name != ident {
-                                quote! {
-                                    fn #push_method_name(&mut self,value:#unwrap_type)->&mut Self{
-                                        match self.#ident{
-                                            Some(ref mut v)=>{v.push(value);},
-                                            None=>{self.#ident=Some(vec![value]);}
-                                        }
-                                        self
-                                    }
-                                    fn #ident(&mut self,value:#ty)->&mut Self{
-                                        self.#ident=Some(value);
-                                        self
-                                    }
-                                }
-                            } else {
-                                quote! {
-                                    fn #push_method_name(&mut self,value:#unwrap_type)->&mut Self{
-                                        match self.#ident{
-                                            Some(ref mut v)=>{v.push(value);},
-                                            None=>{self.#ident=Some(vec![value]);}
-                                        }
-                                        self
-                                    }
-                                }
-                            }
-                        }
-                        _ => {
-                            let error =
-                                syn::Error::new_spanned(ty, "Option need to wrap a single type")
-                                    .to_compile_error();
-                            quote! {#error}
+                );
+                quote! {
+                    #builder_name{
+                        #(#copied_fields,)*
+                        #phantom_default
+                    }
+                }
+            }
+        };
+        let fill = default_with.iter().map(|field| {
+            let Field { ident, kind, .. } = field;
+            let closure = &field.options.default_with;
+            let (value, unset) = match (kind, pattern) {
+                (FieldKind::SubBuilder(_), Pattern::Mutable | Pattern::Immutable) => (
+                    quote!(core::convert::From::from(value)),
+                    quote!(self.#ident.is_none()),
+                ),
+                (FieldKind::SubBuilder(_), _) => (
+                    quote!(core::convert::From::from(value)),
+                    quote!(__builder.#ident.is_none()),
+                ),
+                _ => (quote!(value), quote!(__builder.#ident.is_none())),
+            };
+            quote! {
+                if #unset{
+                    let value=__default_with(&__builder,#closure);
+                    __builder.#ident=core::option::Option::Some(#value);
+                }
+            }
+        });
+        // Passing the closures to a function gives an untyped `|builder|`
+        // its parameter's type.
+        Some(quote! {
+            fn __default_with<__B,__T>(
+                builder:&__B,
+                default_with:impl core::ops::FnOnce(&__B)->__T,
+            )->__T{
+                default_with(builder)
+            }
+            let mut __builder=#copy;
+            #(#fill)*
+        })
+    };
+    let set_members = build_order(fields).map(|field| {
+        let Field { ident, kind, .. } = field;
+        let fallback = fallback(field, options);
+        let take = pattern_in_build.take(quote!(#source.#ident));
+        if let FieldKind::Skipped = kind {
+            quote! {let #ident=#fallback}
+        } else if let FieldKind::SubBuilder(_) = kind {
+            // An unset sub-builder with no fallback is built as is, so the
+            // fields it is missing are reported by their path. Sub-builders
+            // are built through the helpers every builder has for this, which
+            // work whatever their pattern and error type. An owned one can
+            // only be built from behind a reference if it is Clone, which is
+            // reported at the field.
+            let ty = field.ty;
+            let span = syn::spanned::Spanned::span(ty);
+            let build_owned = quote_spanned!(span=> __build_owned);
+            let build_ref = quote_spanned!(span=> __build_ref);
+            let unset = match fallback {
+                Some(fallback) => quote!(core::result::Result::Ok(#fallback)),
+                None => quote!(<#ty>::builder().__build_owned()),
+            };
+            let built = match (pattern_in_build, pattern) {
+                // The copy made for `default_with` only holds what a closure
+                // filled in; whatever was set is still in `self`.
+                (Pattern::Owned, Pattern::Mutable | Pattern::Immutable) => quote! {
+                    match &self.#ident{
+                        core::option::Option::Some(builder)=>builder.#build_ref(),
+                        core::option::Option::None=>match #source.#ident{
+                            core::option::Option::Some(builder)=>builder.#build_owned(),
+                            core::option::Option::None=>#unset,
+                        },
+                    }
+                },
+                (Pattern::Owned | Pattern::Const, _) => quote! {
+                    match #source.#ident{
+                        core::option::Option::Some(builder)=>builder.#build_owned(),
+                        core::option::Option::None=>#unset,
+                    }
+                },
+                (Pattern::Mutable | Pattern::Immutable, _) => quote! {
+                    match &#source.#ident{
+                        core::option::Option::Some(builder)=>builder.#build_ref(),
+                        core::option::Option::None=>#unset,
+                    }
+                },
+            };
+            quote! {
+                let #ident=#built;
+                let #ident=match #ident{
+                    core::result::Result::Ok(v)=>v,
+                    core::result::Result::Err(error)=>{
+                        let (field,message)=error.nested_in(stringify!(#ident));
+                        return core::result::Result::Err(core::convert::From::from(
+                            #error_name::SubField{field,message},
+                        ));
+                    }
+                }
+            }
+        } else {
+            let fallback = fallback.unwrap_or_else(|| match kind {
+                FieldKind::Optional(_) => quote!(core::option::Option::None),
+                _ if pattern == Pattern::Const => quote! {
+                    return core::result::Result::Err(
+                        #error_name::UninitializedField(stringify!(#ident)),
+                    )
+                },
+                _ => quote! {
+                    return core::result::Result::Err(core::convert::From::from(
+                        #error_name::UninitializedField(stringify!(#ident)),
+                    ))
+                },
+            });
+            quote! {
+                let #ident=match #take{
+                    core::option::Option::Some(v)=>v,
+                    core::option::Option::None=>#fallback,
+                }
+            }
+        }
+    });
+    let group_checks = options.groups.iter().map(|group| {
+        let names = &group.fields;
+        let names = quote!(&[#(stringify!(#names)),*]);
+        let count_set = group.fields.iter().map(|name| {
+            quote!(core::option::Option::is_some(&self.#name) as usize)
+        });
+        match group.kind {
+            GroupKind::OneOf => quote! {
+                match 0 #(+#count_set)* {
+                    1=>{}
+                    0=>return core::result::Result::Err(core::convert::From::from(
+                        #error_name::MissingOneOf(#names),
+                    )),
+                    _=>return core::result::Result::Err(core::convert::From::from(
+                        #error_name::ConflictingFields(#names),
+                    )),
+                }
+            },
+            GroupKind::AtMostOneOf => quote! {
+                if 0 #(+#count_set)* >1{
+                    return core::result::Result::Err(core::convert::From::from(
+                        #error_name::ConflictingFields(#names),
+                    ));
+                }
+            },
+            GroupKind::Requires => {
+                let fields = &group.fields;
+                quote! {
+                    {
+                        let set=[#((stringify!(#fields),core::option::Option::is_some(&self.#fields))),*];
+                        let field=core::iter::Iterator::find(&mut set.iter(),|(_,set)|*set);
+                        let required=core::iter::Iterator::find(&mut set.iter(),|(_,set)|!*set);
+                        if let (core::option::Option::Some(&(field,_)),core::option::Option::Some(&(required,_)))=(field,required){
+                            return core::result::Result::Err(core::convert::From::from(
+                                #error_name::RequiresField{field,required},
+                            ));
                         }
+                    }
+                }
+            }
+        }
+    });
+    let struct_default = struct_default(&struct_ty, options);
+    let struct_validation = options.build_fn.validate.as_ref().map(|validate| {
+        quote! {
+            if let core::result::Result::Err(message)=#validate(&self){
+                return core::result::Result::Err(core::convert::From::from(
+                    #error_name::ValidationError(message),
+                ));
+            }
+        }
+    });
+    let field_validations = fields.iter().filter_map(|Field { ident, options, .. }| {
+        let validate = options.validate.as_ref()?;
+        Some(quote! {
+            if let core::result::Result::Err(message)=#validate(&#ident){
+                return core::result::Result::Err(core::convert::From::from(
+                    #error_name::InvalidField{
+                        field:stringify!(#ident),
+                        message,
                     },
-                    Some(Err(err_message)) => err_message,
+                ));
+            }
+        })
+    });
+    let merge_arg = SetterArg::new("other", quote!(Self), Conversion::Exact);
+    let merge_method = pattern.setter(&vis, &quote::format_ident!("merge"), &[merge_arg], |this| {
+        let merge_fields =
+            fields
+                .iter()
+                .filter(|field| field.in_builder())
+                .map(|Field { ident, .. }| {
+                    quote! {
+                        if other.#ident.is_some(){
+                            #this.#ident=other.#ident;
+                        }
+                    }
+                });
+        quote!(#(#merge_fields)*)
+    });
+    let (to_builder, from_value) = if *variant {
+        (None, None)
+    } else {
+        let from_fields = fields.iter().filter(|field| field.in_builder()).map(
+            |Field {
+                 ident,
+                 member,
+                 kind,
+                 ..
+             }| match kind {
+                FieldKind::SubBuilder(_) => quote! {
+                    #ident:core::option::Option::Some(core::convert::From::from(value.#member))
+                },
+                _ => quote!(#ident:core::option::Option::Some(value.#member)),
+            },
+        );
+        let to_builder = to_builder(&vis, &struct_ty, &quote!(#builder_name #ty_generics));
+        let from_value = quote! {
+            impl #impl_generics core::convert::From<#struct_ty> for #builder_name #ty_generics #where_clause{
+                fn from(value:#struct_ty)->Self{
+                    #builder_name{
+                        #(#from_fields,)*
+                        #phantom_default
+                    }
+                }
+            }
+        };
+        (Some(to_builder), Some(from_value))
+    };
+    let constness = pattern.constness();
+    let InfoNames {
+        info: info_name,
+        status: status_name,
+    } = info_names;
+    let field_infos = fields
+        .iter()
+        .filter(|field| field.in_builder())
+        .map(|field| {
+            let name = field.ident.unraw().to_string();
+            let ty = type_name(field.ty);
+            let status = quote::format_ident!("{}", field_status(field, options));
+            quote! {
+                #info_name{
+                    name:#name,
+                    ty:#ty,
+                    status:#status_name::#status,
                 }
             }
         });
-    let members = struct_fields
+    let is_set_methods = fields
         .iter()
-        .map(|field| (field.ident.as_ref().unwrap()));
-    let set_members = struct_fields
+        .filter(|field| field.in_builder())
+        .map(|field| {
+            let ident = &field.ident;
+            let name = ident.unraw();
+            let method = is_set_name(field.setter_name());
+            let setter_vis = field.options.setter_vis(options);
+            let docs = format!("Whether `{}` has been set.", name);
+            quote! {
+                #[doc = #docs]
+                #setter_vis #constness fn #method(&self)->bool{
+                    core::option::Option::is_some(&self.#ident)
+                }
+            }
+        });
+    // A sub-builder counts as missing while its own builder, or an empty one
+    // if it is unset, is missing fields.
+    let missing_checks = fields
         .iter()
-        .map(|field| (field.ident.as_ref().unwrap(), &field.ty))
-        .map(|(ident, ty)| {
-			if is_option(ty).is_some() {
-                quote! {let #ident=self.#ident.clone()}
-            } else if is_vec(ty).is_some(){
-				quote!{
-					let #ident=match self.#ident{
-						None=>Vec::new(),
-						Some(ref v)=>v.clone()
-					}
-				}
-			}else {
-                quote! {let #ident=self.#ident.as_ref().ok_or(concat!("Field ",stringify!(#ident)," not set",))?.clone()}
-            }
-		});
-    let output = quote! {
-        impl #struct_name{
-            fn builder()->#builder_name{
-                #builder_name::default()
+        .filter(|field| field.in_builder())
+        .filter_map(|field| {
+            let ident = &field.ident;
+            let name = ident.unraw().to_string();
+            let ty = field.ty;
+            match field_status(field, options) {
+                "Required" => Some(quote!(core::option::Option::is_none(&self.#ident))),
+                "SubBuilder" => Some(quote! {
+                    match self.#ident{
+                        core::option::Option::Some(ref builder)=>{
+                            !builder.missing_fields().is_empty()
+                        }
+                        core::option::Option::None=>{
+                            !<#ty>::builder().missing_fields().is_empty()
+                        }
+                    }
+                }),
+                _ => None,
             }
+            .map(|missing| {
+                quote! {
+                    if #missing{
+                        missing.push(#name);
+                    }
+                }
+            })
+        });
+    let vec = if options.no_std {
+        quote!(alloc::vec::Vec)
+    } else {
+        quote!(std::vec::Vec)
+    };
+    let build_receiver = pattern.build_receiver();
+    // A reusable builder's build() clones the values out of it, so it only
+    // exists where they are Clone. Each bound is spanned at its field, and
+    // bounds on the type parameters are left to the struct's own where clause.
+    let build_bounds = match pattern {
+        Pattern::Mutable | Pattern::Immutable => {
+            let bounds: Vec<_> = fields
+                .iter()
+                .filter(|field| field.in_builder())
+                .filter(|field| !matches!(field.kind, FieldKind::SubBuilder(_)))
+                .map(|field| {
+                    let ty = field.ty;
+                    let span = syn::spanned::Spanned::span(ty);
+                    quote_spanned!(span=> #ty: core::clone::Clone)
+                })
+                .collect();
+            if bounds.is_empty() {
+                None
+            } else {
+                Some(quote!(where #(#bounds),*))
+            }
+        }
+        Pattern::Owned | Pattern::Const => None,
+    };
+    // build() itself fails with the error type the user asked for. Builders
+    // holding this one as a sub-builder need the derive's own error to nest,
+    // whichever it is, and to build it both from a reference and by value.
+    // The `for<'__b>` delays the Clone bound as in [`to_builder`].
+    let build_result = match options.error {
+        Some(_) => quote! {
+            match Self::__build(self){
+                core::result::Result::Ok(v)=>core::result::Result::Ok(v),
+                core::result::Result::Err(error)=>core::result::Result::Err(core::convert::From::from(error)),
+            }
+        },
+        None => quote!(Self::__build(self)),
+    };
+    let build_helpers = match pattern {
+        Pattern::Owned | Pattern::Const => quote! {
+            #[doc(hidden)]
+            #[allow(dead_code)]
+            #vis fn __build_owned(self)->core::result::Result<#struct_ty, #error_name>{
+                Self::__build(self)
+            }
+            #[doc(hidden)]
+            #[allow(dead_code)]
+            #vis fn __build_ref(&self)->core::result::Result<#struct_ty, #error_name>
+            where
+                for<'__b> Self: core::clone::Clone,
+            {
+                Self::__build(core::clone::Clone::clone(self))
+            }
+        },
+        Pattern::Mutable | Pattern::Immutable => quote! {
+            #[doc(hidden)]
+            #[allow(dead_code)]
+            #vis fn __build_owned(self)->core::result::Result<#struct_ty, #error_name>
+            #build_bounds
+            {
+                Self::__build(&self)
+            }
+            #[doc(hidden)]
+            #[allow(dead_code)]
+            #vis fn __build_ref(&self)->core::result::Result<#struct_ty, #error_name>
+            #build_bounds
+            {
+                Self::__build(self)
+            }
+        },
+    };
+    let build_name = options.build_fn_name();
+    let builder_derive = builder_derive(options);
+    // A deserialized builder leaves the fields missing from the input unset.
+    let serde_derive = if options.serde {
+        Some(quote! {
+            #[derive(::serde::Deserialize)]
+            #[serde(default)]
+        })
+    } else {
+        None
+    };
+    // Default::default() is not a const fn, so a const builder spells out
+    // its empty state.
+    let constructor_body = match constness {
+        Some(_) => quote! {
+            #builder_name{
+                #(#builder_defaults,)*
+                #phantom_default
+            }
+        },
+        None => quote!(core::default::Default::default()),
+    };
+    let doc_path = doc_path(path);
+    let constructor_docs = format!("A builder for [`{}`], with no field set.", doc_path);
+    let builder_docs = match options.doc {
+        Some(ref doc) => quote!(#[doc = #doc]),
+        None => {
+            let doc = format!("Builder for [`{}`].", doc_path);
+            quote!(#[doc = #doc])
+        }
+    };
+    let build_docs = format!(
+        "Builds the [`{}`]. Fails if a required field is unset or a validator rejects a value.",
+        doc_path
+    );
+    quote! {
+        impl #impl_generics #struct_name #ty_generics #where_clause{
+            #[doc = #constructor_docs]
+            #vis #constness fn #constructor()->#builder_name #ty_generics{
+                #constructor_body
+            }
+            #to_builder
         }
+        #builder_docs
         #[allow(dead_code)]
-        #[derive(Default)]
-        struct #builder_name{
-            #(#builder_fields),*
+        #builder_derive
+        #serde_derive
+        #vis struct #builder_name #generics #where_clause{
+            #(#builder_fields,)*
+            #phantom_field
         }
 
-        impl #builder_name{
+        impl #impl_generics core::default::Default for #builder_name #ty_generics #where_clause{
+            fn default()->Self{
+                #builder_name{
+                    #(#builder_defaults,)*
+                    #phantom_default
+                }
+            }
+        }
+
+        #from_value
+
+        impl #impl_generics #builder_name #ty_generics #where_clause{
             #(#builder_methods)*
+            /// Takes every field set in `other`, keeping this builder's values
+            /// for the rest.
+            #merge_method
         }
-        impl #builder_name{
-            fn build(&self)->std::result::Result<#struct_name, std::boxed::Box<dyn std::error::Error>>{
+        impl #impl_generics #builder_name #ty_generics #where_clause{
+            /// Every field of the builder, in declaration order.
+            #vis const FIELDS:&'static [#info_name]=&[#(#field_infos),*];
+            #(#is_set_methods)*
+            /// The fields `build()` would report as unset, in declaration
+            /// order. Validators and field groups are not checked.
+            #vis fn missing_fields(&self)->#vec<&'static str>{
+                let mut missing=#vec::new();
+                #(#missing_checks)*
+                missing
+            }
+        }
+        impl #impl_generics #builder_name #ty_generics #where_clause{
+            #[doc = #build_docs]
+            #vis #constness fn #build_name(#build_receiver)->core::result::Result<#struct_ty, #build_error>
+            #build_bounds
+            {
+                #build_result
+            }
+            #build_helpers
+            #[doc(hidden)]
+            #vis #constness fn __build(#build_receiver)->core::result::Result<#struct_ty, #error_name>
+            #build_bounds
+            {
+                #(#group_checks)*
+                #struct_validation
+                #default_with_copy
+                #struct_default
                 #(#set_members;)*
-                Ok(#struct_name{
-                    #(#members:#members),*
+                #(#field_validations)*
+                core::result::Result::Ok(#path{
+                    #(#members),*
                 })
             }
         }
+    }
+}
+
+/// The `to_builder()` method of the struct, returning `builder` with every
+/// field set from a clone of the value.
+///
+/// It requires the struct to be Clone. The bound is wrapped in `for<'__b>`
+/// because for a struct without type parameters it would otherwise be checked
+/// where the method is defined, and a struct that isn't Clone would fail to
+/// compile instead of only lacking the method. For a generic struct the
+/// wrapper makes no difference.
+fn to_builder(
+    vis: &syn::Visibility,
+    struct_ty: &proc_macro2::TokenStream,
+    builder: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    quote! {
+        /// A builder with every field set from this value, to build a
+        /// modified copy of it.
+        #vis fn to_builder(&self)->#builder
+        where
+            for<'__b> #struct_ty: core::clone::Clone,
+        {
+            core::convert::From::from(core::clone::Clone::clone(self))
+        }
+    }
+}
+
+/// `#[derive(...)]` for the builder: whatever was asked for, plus `Clone`
+/// which the immutable pattern needs for its setters.
+fn builder_derive(options: &StructOptions) -> Option<proc_macro2::TokenStream> {
+    let mut derive = options.derive.clone();
+    if options.pattern == Pattern::Immutable && !derive.iter().any(|path| path.is_ident("Clone")) {
+        derive.push(syn::parse_quote!(Clone));
+    }
+    if derive.is_empty() {
+        None
+    } else {
+        Some(quote!(#[derive(#(#derive),*)]))
+    }
+}
+
+/// The error returned by every builder generated for one derive input. Its
+/// `SubField` variant only exists if one of the builders has a sub-builder,
+/// the field group variants if the struct has field groups, and the
+/// validation variants unless the builder is `const`.
+fn error_enum(
+    error_name: &syn::Ident,
+    options: &StructOptions,
+    sub_builders: bool,
+) -> proc_macro2::TokenStream {
+    let groups = !options.groups.is_empty();
+    let vis = options.vis();
+    let alloc = if options.no_std {
+        quote!(alloc)
+    } else {
+        quote!(std)
     };
-    output.into()
+    let string = quote!(#alloc::string::String);
+    let error_impl = if options.no_std {
+        None
+    } else {
+        Some(quote!(impl std::error::Error for #error_name{}))
+    };
+    // A const builder can't run validators, and leaves out their variants so
+    // the error has nothing to drop when a constant expression discards it.
+    let (validation_fields, validation_display, validation_nested) = if options.const_fn {
+        (None, None, None)
+    } else {
+        (
+            Some(quote! {
+                /// The builder's contents were rejected by a validator.
+                ValidationError(#string),
+                /// A field's value was rejected by its validator.
+                InvalidField{
+                    /// The field at fault.
+                    field:&'static str,
+                    /// Why its validator rejected it.
+                    message:#string,
+                },
+            }),
+            Some(quote! {
+                #error_name::ValidationError(message)=>f.write_str(message),
+                #error_name::InvalidField{field,message}=>{
+                    write!(f,"invalid value for field `{}`: {}",field,message)
+                }
+            }),
+            Some(quote! {
+                #error_name::ValidationError(message)=>(#string::from(parent),message),
+                #error_name::InvalidField{field,message}=>{
+                    (#alloc::format!("{}.{}",parent,field),message)
+                }
+            }),
+        )
+    };
+    let (sub_field, sub_field_display, sub_field_nested) = if sub_builders {
+        (
+            Some(quote! {
+                /// A sub-builder failed to build.
+                SubField{
+                    /// The path to the field at fault, like `tls.cert`.
+                    field:#string,
+                    /// What went wrong with it.
+                    message:#string,
+                },
+            }),
+            Some(quote! {
+                #error_name::SubField{field,message}=>write!(f,"{}: {}",field,message),
+            }),
+            Some(quote! {
+                #error_name::SubField{field,message}=>{
+                    (#alloc::format!("{}.{}",parent,field),message)
+                }
+            }),
+        )
+    } else {
+        (None, None, None)
+    };
+    let (group_fields, group_display, group_nested) = if groups {
+        (
+            Some(quote! {
+                /// None of the fields of a `one_of` group was set.
+                MissingOneOf(&'static [&'static str]),
+                /// More than one field of a `one_of` or `at_most_one_of` group
+                /// was set.
+                ConflictingFields(&'static [&'static str]),
+                /// A field of a `requires` group was set without the rest of
+                /// the group.
+                RequiresField{
+                    /// The field that was set.
+                    field:&'static str,
+                    /// A field of its group that was not.
+                    required:&'static str,
+                },
+            }),
+            Some(quote! {
+                #error_name::MissingOneOf(fields)=>{
+                    f.write_str("one of ")?;
+                    write_fields(f,fields)?;
+                    f.write_str(" must be set")
+                }
+                #error_name::ConflictingFields(fields)=>{
+                    f.write_str("only one of ")?;
+                    write_fields(f,fields)?;
+                    f.write_str(" can be set")
+                }
+                #error_name::RequiresField{field,required}=>{
+                    write!(f,"field `{}` requires `{}` to be set too",field,required)
+                }
+            }),
+            Some(quote! {
+                error=>(#string::from(parent),#alloc::format!("{}",error)),
+            }),
+        )
+    } else {
+        (None, None, None)
+    };
+    let write_fields = if groups {
+        Some(quote! {
+            fn write_fields(
+                f:&mut core::fmt::Formatter<'_>,
+                fields:&[&str],
+            )->core::fmt::Result{
+                for (i,field) in fields.iter().enumerate(){
+                    if i>0{
+                        f.write_str(", ")?;
+                    }
+                    write!(f,"`{}`",field)?;
+                }
+                core::result::Result::Ok(())
+            }
+        })
+    } else {
+        None
+    };
+    quote! {
+        /// The error returned when a builder fails to build.
+        #[allow(dead_code)]
+        #[derive(Debug)]
+        #vis enum #error_name{
+            /// A required field was never set.
+            UninitializedField(&'static str),
+            #validation_fields
+            #sub_field
+            #group_fields
+        }
+
+        impl #error_name{
+            /// Splits the error into the path of the field at fault, starting
+            /// from `parent`, and what went wrong with it. This is how the
+            /// builder of a struct holding this one as a sub-builder reports
+            /// the error.
+            #vis fn nested_in(self,parent:&str)->(#string,#string){
+                match self{
+                    #error_name::UninitializedField(field)=>{
+                        (#alloc::format!("{}.{}",parent,field),#string::from("not set"))
+                    }
+                    #validation_nested
+                    #sub_field_nested
+                    #group_nested
+                }
+            }
+        }
+
+        impl core::fmt::Display for #error_name{
+            fn fmt(&self,f:&mut core::fmt::Formatter<'_>)->core::fmt::Result{
+                #write_fields
+                match self{
+                    #error_name::UninitializedField(field)=>write!(f,"field `{}` not set",field),
+                    #validation_display
+                    #sub_field_display
+                    #group_display
+                }
+            }
+        }
+
+        #error_impl
+    }
+}
+
+/// The description of builder fields in `FIELDS`, and the status telling what
+/// `build()` does with a field left unset. Both are shared by every builder
+/// generated for one derive input.
+fn field_info(names: &InfoNames, options: &StructOptions) -> proc_macro2::TokenStream {
+    let InfoNames { info, status } = names;
+    let vis = options.vis();
+    quote! {
+        /// One field of a builder, as listed in its `FIELDS`.
+        #[allow(dead_code)]
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        #vis struct #info{
+            /// The field's name.
+            #vis name:&'static str,
+            /// The field's type, as written in the struct.
+            #vis ty:&'static str,
+            /// What `build()` does if the field is never set.
+            #vis status:#status,
+        }
+
+        /// What `build()` does with a builder field that is never set.
+        #[allow(dead_code)]
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        #vis enum #status{
+            /// `build()` fails.
+            Required,
+            /// An `Option` field, left `None`.
+            Optional,
+            /// The field's default is used.
+            Defaulted,
+            /// A collection filled one item at a time, left empty.
+            Each,
+            /// Built from an empty builder of its own, so it is only missing
+            /// if that builder is.
+            SubBuilder,
+        }
+    }
+}
+
+/// `ConnectRequest` to `connect_request`, and `HTTPGet` to `http_get`.
+fn snake_case(ident: &syn::Ident) -> String {
+    let name = ident.unraw().to_string();
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() {
+            // A capital starts a word after a lowercase letter or digit, and
+            // a run of capitals like `HTTP` ends with the word after it.
+            let after_word = i > 0 && (chars[i - 1].is_lowercase() || chars[i - 1].is_numeric());
+            let ends_run = i > 0
+                && chars[i - 1].is_uppercase()
+                && chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if after_word || ends_run {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+/// Matches `Wrapper<..>` as well as the full path to it through the
+/// standard library, like `std::vec::Vec<..>`.
+fn is_wrapped(ty: &syn::Type, module: &str, wrapper: &str) -> Option<syn::PathArguments> {
+    match ty {
+        syn::Type::Path(
+            syn::TypePath {
+                qself: None,
+                path: syn::Path { segments: s, .. },
+            },
+            ..,
+        ) if s.last()?.ident == wrapper
+            && (s.len() == 1
+                || s.len() == 3
+                    && ["std", "core", "alloc"].iter().any(|c| s[0].ident == c)
+                    && s[1].ident == module) =>
+        {
+            Some(s.last()?.arguments.clone())
+        }
+        _ => None,
+    }
+}
+
+fn is_option(ty: &syn::Type) -> Option<syn::PathArguments> {
+    is_wrapped(ty, "option", "Option")
+}
+
+fn is_result(ty: &syn::Type) -> Option<syn::PathArguments> {
+    is_wrapped(ty, "result", "Result")
+}
+
+/// The error type `E` of `Result<T, E>`.
+fn result_error(ty: &syn::Type) -> Option<syn::Type> {
+    match is_result(ty)? {
+        syn::PathArguments::AngleBracketed(arguments) if arguments.args.len() == 2 => {
+            match arguments.args.last()? {
+                syn::GenericArgument::Type(error) => Some(error.clone()),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn is_vec(ty: &syn::Type) -> Option<syn::PathArguments> {
+    is_wrapped(ty, "vec", "Vec")
+}
+
+/// `clear_{field}`, the method resetting an optional field.
+fn clear_name(setter_name: &syn::Ident) -> syn::Ident {
+    quote::format_ident!("clear_{}", setter_name.to_string().trim_start_matches("r#"))
+}
+
+/// `is_set_{field}`, the method telling whether a field has been set.
+fn is_set_name(setter_name: &syn::Ident) -> syn::Ident {
+    quote::format_ident!(
+        "is_set_{}",
+        setter_name.to_string().trim_start_matches("r#")
+    )
+}
+
+/// `extend_{field}`, the method adding many items to an `each` field.
+fn extend_name(setter_name: &syn::Ident) -> syn::Ident {
+    quote::format_ident!(
+        "extend_{}",
+        setter_name.to_string().trim_start_matches("r#")
+    )
+}
+
+/// Returns the single type argument of `Option<T>` or `Vec<T>`.
+fn single_argument(arguments: &syn::PathArguments) -> Option<&syn::GenericArgument> {
+    match arguments {
+        syn::PathArguments::AngleBracketed(syn::AngleBracketedGenericArguments {
+            args, ..
+        }) if args.len() == 1 => args.first(),
+        _ => None,
+    }
+}
+
+/// A field of the struct or variant being built.
+struct Field<'a> {
+    /// Name of the field's slot in the builder, its setter and the local
+    /// holding its value in `build()`. For tuple fields this is `_0`, `_1`,
+    /// ... unless renamed with `#[builder(name = "...")]`.
+    ident: syn::Ident,
+    /// How the target names the field.
+    member: syn::Member,
+    ty: &'a syn::Type,
+    kind: FieldKind,
+    options: FieldOptions,
+    /// The field's `///` docs, repeated on its setters.
+    docs: Vec<&'a syn::Attribute>,
+}
+
+/// What the setters of a field look like.
+#[allow(clippy::large_enum_variant)]
+enum FieldKind {
+    /// One setter taking the field's type.
+    Plain,
+    /// An `Option<T>` field, whose setter takes `T` unless
+    /// `setter(strip_option = false)`, plus a `clear_{field}` method. The
+    /// builder stores `Option<Option<T>>`, so an explicit `None` is told apart
+    /// from never setting the field.
+    Optional(syn::Type),
+    /// A collection with `#[builder(each = "...")]`, filled one item at a time
+    /// by the named method, or in bulk by `extend_{field}`. The type is that
+    /// of the collection if the field is an `Option` of it, which the first
+    /// item turns from `None` into `Some`.
+    Each(syn::Ident, EachItem, Option<syn::Type>),
+    /// `#[builder(skip)]`: no slot in the builder, only ever its fallback.
+    Skipped,
+    /// `#[builder(sub_builder)]`: the builder holds the field type's own
+    /// builder, reached through a `{field}_mut` method, and builds it in
+    /// `build()`.
+    SubBuilder(syn::Type),
+}
+
+impl<'a> Field<'a> {
+    /// Whether the builder has a slot for this field.
+    fn in_builder(&self) -> bool {
+        !matches!(self.kind, FieldKind::Skipped)
+    }
+
+    /// Whether the builder has a setter for this field, generated or custom.
+    fn has_setter(&self) -> bool {
+        self.in_builder() && !self.options.setter.skip
+    }
+
+    /// Whether the setter of this field is generated rather than written by
+    /// hand with `setter(custom)`.
+    fn generates_setter(&self) -> bool {
+        self.has_setter() && self.options.setter.custom.is_none()
+    }
+
+    /// Name of the setter assigning the whole field.
+    fn setter_name(&self) -> &syn::Ident {
+        self.options.setter.name.as_ref().unwrap_or(&self.ident)
+    }
+
+    /// Parses the fields' attributes and checks that they make sense for the
+    /// fields' types, recording every problem in `errors`.
+    fn parse_all(
+        fields: &'a syn::Fields,
+        options: &StructOptions,
+        errors: &mut Errors,
+    ) -> Vec<Self> {
+        let fields: Vec<Self> = fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| {
+                let field_options = FieldOptions::from_attrs(&field.attrs, errors);
+                let member = match field.ident {
+                    Some(ref ident) => syn::Member::Named(ident.clone()),
+                    None => syn::Member::Unnamed(syn::Index::from(index)),
+                };
+                let ident = match (&field_options.name, &field.ident) {
+                    (Some(name), _) => name.clone(),
+                    (None, Some(ident)) => ident.clone(),
+                    (None, None) => quote::format_ident!("_{}", index),
+                };
+                let kind = errors
+                    .ok(FieldKind::new(&field.ty, &field_options, options))
+                    .unwrap_or(FieldKind::Plain);
+                if let (true, Some(validate)) = (options.typestate, &field_options.validate) {
+                    errors.push(syn::Error::new_spanned(
+                        validate,
+                        "a typestate builder cannot be validated because its `build()` cannot fail",
+                    ));
+                }
+                if let (true, Some(sub_builder)) = (options.typestate, &field_options.sub_builder) {
+                    errors.push(syn::Error::new_spanned(
+                        &sub_builder.path,
+                        "sub-builders are not supported by typestate builders, as building one can fail",
+                    ));
+                }
+                if let (true, Some(custom)) = (options.typestate, &field_options.setter.custom) {
+                    errors.push(syn::Error::new_spanned(
+                        custom,
+                        "custom setters are not supported by typestate builders",
+                    ));
+                }
+                if let (true, Some(default_with)) = (options.typestate, &field_options.default_with)
+                {
+                    errors.push(syn::Error::new_spanned(
+                        default_with,
+                        "`default_with` is not supported by typestate builders",
+                    ));
+                }
+                Field {
+                    ident,
+                    member,
+                    ty: &field.ty,
+                    kind,
+                    options: field_options,
+                    docs: field
+                        .attrs
+                        .iter()
+                        .filter(|attr| attr.path.is_ident("doc"))
+                        .collect(),
+                }
+            })
+            .collect();
+        if let Err(error) = default_with_order(&fields) {
+            errors.push(error);
+        }
+        if let Pattern::Mutable | Pattern::Immutable = options.pattern {
+            check_sub_builder_reads(&fields, errors);
+        }
+        if options.const_fn {
+            for field in &fields {
+                field.check_const(errors);
+            }
+        }
+        fields
+    }
+
+    /// Reports what keeps a `const` builder from setting or building this
+    /// field in a `const fn`.
+    fn check_const(&self, errors: &mut Errors) {
+        let options = &self.options;
+        let mut reject = |tokens: &dyn quote::ToTokens, message: &str| {
+            errors.push(syn::Error::new_spanned(tokens, message));
+        };
+        if let Some(ref each) = options.each {
+            reject(each, "`each` is not supported by `const` builders");
+        }
+        if let Some(ref sub_builder) = options.sub_builder {
+            reject(
+                &sub_builder.path,
+                "`sub_builder` is not supported by `const` builders",
+            );
+        }
+        if options.setter.conversion.is_some() {
+            reject(
+                &self.ident,
+                "`into` and `try_into` setters cannot be `const fn`s",
+            );
+        }
+        if let Some(ref with) = options.setter.with {
+            reject(with, "`with` closures cannot be called in a `const fn`");
+        }
+        if let Some(ref default_with) = options.default_with {
+            reject(
+                default_with,
+                "`default_with` closures cannot be called in a `const fn`",
+            );
+        }
+        if let Some(ref validate) = options.validate {
+            reject(validate, "a `const` builder cannot call validators");
+        }
+        if let Some(heap) = heap_type(self.ty) {
+            let message = format!(
+                "a `const` builder cannot hold a `{}`, as it owns heap memory",
+                heap
+            );
+            reject(heap, &message);
+        } else if matches!(options.default, Some(FieldDefault::Trait))
+            || options.default.is_none() && (options.skip || options.setter.skip)
+        {
+            reject(
+                &self.ident,
+                "this field defaults to `Default::default()`, which is not a `const fn`; give its default as an expression",
+            );
+        }
+    }
+}
+
+/// The first standard collection or smart pointer in `ty`, which a `const`
+/// builder can neither create nor drop.
+fn heap_type(ty: &syn::Type) -> Option<&syn::Ident> {
+    const HEAP_TYPES: &[&str] = &[
+        "Vec",
+        "String",
+        "Box",
+        "Rc",
+        "Arc",
+        "HashMap",
+        "HashSet",
+        "BTreeMap",
+        "BTreeSet",
+        "VecDeque",
+        "BinaryHeap",
+        "LinkedList",
+    ];
+    match ty {
+        syn::Type::Path(syn::TypePath { path, .. }) => {
+            let segment = path.segments.last()?;
+            if HEAP_TYPES.iter().any(|heap| segment.ident == heap) {
+                return Some(&segment.ident);
+            }
+            match segment.arguments {
+                syn::PathArguments::AngleBracketed(ref arguments) => {
+                    arguments.args.iter().find_map(|argument| match argument {
+                        syn::GenericArgument::Type(ty) => heap_type(ty),
+                        _ => None,
+                    })
+                }
+                _ => None,
+            }
+        }
+        syn::Type::Tuple(tuple) => tuple.elems.iter().find_map(heap_type),
+        syn::Type::Array(array) => heap_type(&array.elem),
+        syn::Type::Paren(paren) => heap_type(&paren.elem),
+        syn::Type::Group(group) => heap_type(&group.elem),
+        _ => None,
+    }
+}
+
+impl FieldKind {
+    fn new(
+        ty: &syn::Type,
+        field_options: &FieldOptions,
+        options: &StructOptions,
+    ) -> syn::Result<Self> {
+        if field_options.skip {
+            return Ok(FieldKind::Skipped);
+        }
+        if let Some(arguments) = is_option(ty) {
+            if let Some(ref sub_builder) = field_options.sub_builder {
+                return Err(syn::Error::new_spanned(
+                    &sub_builder.path,
+                    "`sub_builder` does not support `Option` fields",
+                ));
+            }
+            let inner = match single_argument(&arguments) {
+                Some(syn::GenericArgument::Type(inner)) => inner,
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        ty,
+                        "Option need to wrap a single type",
+                    ))
+                }
+            };
+            return match field_options.each {
+                Some(ref push_method_name) => {
+                    FieldKind::each(push_method_name, inner, true, field_options, options)
+                }
+                None => Ok(FieldKind::Optional(inner.clone())),
+            };
+        }
+        if let Some(ref strip_option) = field_options.setter.strip_option {
+            return Err(syn::Error::new_spanned(
+                strip_option,
+                "`strip_option` only applies to `Option` fields",
+            ));
+        }
+        if let Some(ref sub_builder) = field_options.sub_builder {
+            return match sub_builder.builder.clone().or_else(|| sub_builder_type(ty)) {
+                Some(builder) => Ok(FieldKind::SubBuilder(builder)),
+                None => Err(syn::Error::new_spanned(
+                    ty,
+                    "cannot tell the name of this type's builder, give it as `sub_builder = \"...\"`",
+                )),
+            };
+        }
+        match field_options.each {
+            Some(ref push_method_name) => {
+                FieldKind::each(push_method_name, ty, false, field_options, options)
+            }
+            None => Ok(FieldKind::Plain),
+        }
+    }
+
+    /// An `each` field holding a `collection`, or an `Option` of one.
+    fn each(
+        push_method_name: &syn::Ident,
+        collection: &syn::Type,
+        optional: bool,
+        field_options: &FieldOptions,
+        options: &StructOptions,
+    ) -> syn::Result<Self> {
+        match each_item(collection) {
+            Some(EachItem::Pair(..))
+                if field_options.conversion(options) == Conversion::TryInto =>
+            {
+                Err(syn::Error::new_spanned(
+                    push_method_name,
+                    "`try_into` setters are not supported for map entries",
+                ))
+            }
+            Some(item) => {
+                let optional = if optional {
+                    Some(collection.clone())
+                } else {
+                    None
+                };
+                Ok(FieldKind::Each(push_method_name.clone(), item, optional))
+            }
+            None => Err(syn::Error::new_spanned(
+                collection,
+                "`each` requires a collection type such as `Vec<T>` or `HashMap<K, V>`",
+            )),
+        }
+    }
+}
+
+/// `{Type}Builder` for a field of type `Type`, keeping its path and generic
+/// arguments.
+fn sub_builder_type(ty: &syn::Type) -> Option<syn::Type> {
+    let mut path = match ty {
+        syn::Type::Path(syn::TypePath { qself: None, path }) => path.clone(),
+        _ => return None,
+    };
+    let segment = path.segments.last_mut()?;
+    segment.ident = quote::format_ident!("{}Builder", segment.ident);
+    Some(syn::Type::Path(syn::TypePath { qself: None, path }))
+}
+
+/// The order in which `build()` works out the fields' values: fields without
+/// a setter come last, so their `default` expressions can use the values of
+/// the other fields by name.
+fn build_order<'a, 'b>(fields: &'b [Field<'a>]) -> impl Iterator<Item = &'b Field<'a>> {
+    let (set, computed): (Vec<_>, Vec<_>) = fields.iter().partition(|field| field.has_setter());
+    set.into_iter().chain(computed)
+}
+
+/// The fields with a `default_with` closure, ordered so that each one comes
+/// after those whose builder slots its closure reads, as `build()` fills them
+/// in before calling it. A closure using the builder other than by reading
+/// its fields is taken to read all of them. Fails if the closures depend on
+/// each other in a cycle.
+fn default_with_order<'a, 'b>(fields: &'b [Field<'a>]) -> syn::Result<Vec<&'b Field<'a>>> {
+    fn visit<'a, 'b>(
+        index: usize,
+        fields: &'b [Field<'a>],
+        path: &mut Vec<usize>,
+        order: &mut Vec<&'b Field<'a>>,
+    ) -> syn::Result<()> {
+        let field = &fields[index];
+        let closure = match field.options.default_with {
+            Some(ref closure) => closure,
+            None => return Ok(()),
+        };
+        if order.iter().any(|done| done.ident == field.ident) {
+            return Ok(());
+        }
+        if let Some(start) = path.iter().position(|&on_path| on_path == index) {
+            let cycle: Vec<_> = path[start..]
+                .iter()
+                .chain(Some(&index))
+                .map(|&index| format!("`{}`", fields[index].ident.unraw()))
+                .collect();
+            let opaque = path[start..].iter().any(|&index| {
+                let closure = fields[index].options.default_with.as_ref();
+                closure.is_some_and(|closure| builder_reads(closure).is_none())
+            });
+            let note = if opaque {
+                " (a closure that uses the builder other than as `builder.field` is taken to \
+                 read every field)"
+            } else {
+                ""
+            };
+            return Err(syn::Error::new_spanned(
+                closure,
+                format!(
+                    "the `default_with` closures of these fields depend on each other: {}{}",
+                    cycle.join(" -> "),
+                    note
+                ),
+            ));
+        }
+        path.push(index);
+        let dependencies: Vec<usize> = match builder_reads(closure) {
+            Some(reads) => reads
+                .iter()
+                .filter_map(|read| fields.iter().position(|other| other.ident == *read))
+                .collect(),
+            None => (0..fields.len()).filter(|&other| other != index).collect(),
+        };
+        for dependency in dependencies {
+            visit(dependency, fields, path, order)?;
+        }
+        path.pop();
+        order.push(field);
+        Ok(())
+    }
+
+    let mut order = Vec::new();
+    for index in 0..fields.len() {
+        visit(index, fields, &mut Vec::new(), &mut order)?;
+    }
+    Ok(order)
+}
+
+/// A mutable or immutable builder can't copy its sub-builders for the
+/// `default_with` closures to look at, as they need not be `Clone`, so the
+/// closures may not read them.
+fn check_sub_builder_reads(fields: &[Field], errors: &mut Errors) {
+    for field in fields {
+        let closure = match field.options.default_with {
+            Some(ref closure) => closure,
+            None => continue,
+        };
+        for read in builder_reads(closure).unwrap_or_default() {
+            let sub_builder = fields
+                .iter()
+                .any(|other| other.ident == read && matches!(other.kind, FieldKind::SubBuilder(_)));
+            if sub_builder {
+                errors.push(syn::Error::new_spanned(
+                    closure,
+                    format!(
+                        "`default_with` closures can only read the sub-builder `{}` with \
+                         `pattern = \"owned\"`",
+                        read.unraw()
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+/// The builder slots a `default_with` closure reads, as `builder.field`, or
+/// `None` if it also uses the builder in some other way, such as passing it
+/// to a function or calling a method on it, which could read any field.
+fn builder_reads(closure: &syn::ExprClosure) -> Option<Vec<syn::Ident>> {
+    fn scan(
+        tokens: proc_macro2::TokenStream,
+        param: &syn::Ident,
+        reads: &mut Vec<syn::Ident>,
+    ) -> Option<()> {
+        let tokens: Vec<_> = tokens.into_iter().collect();
+        for (i, token) in tokens.iter().enumerate() {
+            match token {
+                proc_macro2::TokenTree::Ident(base) if base == param => {
+                    let member = match (tokens.get(i + 1), tokens.get(i + 2)) {
+                        (
+                            Some(proc_macro2::TokenTree::Punct(dot)),
+                            Some(proc_macro2::TokenTree::Ident(member)),
+                        ) if dot.as_char() == '.' => member,
+                        _ => return None,
+                    };
+                    // `builder.method(..)` and `builder.method::<..>(..)`.
+                    match tokens.get(i + 3) {
+                        Some(proc_macro2::TokenTree::Group(group))
+                            if group.delimiter() == proc_macro2::Delimiter::Parenthesis =>
+                        {
+                            return None
+                        }
+                        Some(proc_macro2::TokenTree::Punct(colon)) if colon.as_char() == ':' => {
+                            return None
+                        }
+                        _ => reads.push(member.clone()),
+                    }
+                }
+                proc_macro2::TokenTree::Group(group) => scan(group.stream(), param, reads)?,
+                _ => {}
+            }
+        }
+        Some(())
+    }
+
+    let mut reads = Vec::new();
+    if let Some(param) = options::default_with_param(closure) {
+        let body = &closure.body;
+        scan(quote!(#body), param, &mut reads)?;
+    }
+    Some(reads)
+}
+
+/// What a single call to an `each` method adds to the collection.
+#[allow(clippy::large_enum_variant)]
+enum EachItem {
+    /// `Vec<T>`, `HashSet<T>` and other collections of `T`: the method takes
+    /// `value: T`.
+    Single(syn::Type),
+    /// `HashMap<K, V>` and `BTreeMap<K, V>`: the method takes `key: K` and
+    /// `value: V`.
+    Pair(syn::Type, syn::Type),
+}
+
+impl EachItem {
+    /// Arguments of the `each` method.
+    fn args(&self, conversion: Conversion) -> Vec<SetterArg> {
+        match self {
+            EachItem::Single(ty) => vec![SetterArg::new("value", ty, conversion)],
+            EachItem::Pair(key, value) => vec![
+                SetterArg::new("key", key, conversion),
+                SetterArg::new("value", value, conversion),
+            ],
+        }
+    }
+
+    /// The iterator over the single item added by the `each` method.
+    fn once(&self) -> proc_macro2::TokenStream {
+        match self {
+            EachItem::Single(_) => quote!(core::iter::once(value)),
+            EachItem::Pair(..) => quote!(core::iter::once((key, value))),
+        }
+    }
+
+    /// Signature of `extend_{field}`, taking any iterable of items.
+    fn extend_signature(&self) -> SetterSignature {
+        let item = match self {
+            EachItem::Single(ty) => quote!(#ty),
+            EachItem::Pair(key, value) => quote!((#key, #value)),
+        };
+        SetterSignature {
+            generics: vec![quote!(__I:core::iter::IntoIterator<Item=#item>)],
+            params: vec![quote!(items:__I)],
+            conversions: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Statement adding `items` to the `Option<collection>` at `place`,
+    /// starting from an empty collection if it is still unset. The trait
+    /// calls are spanned at the collection's type, so that a type without
+    /// `Default` or `Extend` is reported at the field.
+    fn extend(
+        collection: &syn::Type,
+        place: &proc_macro2::TokenStream,
+        items: &proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let span = syn::spanned::Spanned::span(collection);
+        let default = quote_spanned!(span=> <#collection as core::default::Default>::default);
+        let extend = quote_spanned!(span=> <#collection as core::iter::Extend<_>>::extend);
+        quote! {
+            #extend(#place.get_or_insert_with(#default), #items);
+        }
+    }
+
+    /// The `Option<collection>` to extend in the builder slot `slot` of an
+    /// `each` field, which holds another `Option` if the field is one.
+    fn place(slot: proc_macro2::TokenStream, optional: bool) -> proc_macro2::TokenStream {
+        if optional {
+            quote!((*#slot.get_or_insert(core::option::Option::None)))
+        } else {
+            slot
+        }
+    }
+}
+
+/// Works out the item type of an `each` collection from its type arguments.
+/// The collection itself must implement `Default` and `Extend` of that item.
+fn each_item(ty: &syn::Type) -> Option<EachItem> {
+    let segment = match ty {
+        syn::Type::Path(syn::TypePath { qself: None, path }) => path.segments.last()?,
+        _ => return None,
+    };
+    let types: Vec<&syn::Type> = match segment.arguments {
+        syn::PathArguments::AngleBracketed(ref arguments) => arguments
+            .args
+            .iter()
+            .filter_map(|argument| match argument {
+                syn::GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .collect(),
+        _ => return None,
+    };
+    match types[..] {
+        [key, value, ..] if segment.ident == "HashMap" || segment.ident == "BTreeMap" => {
+            Some(EachItem::Pair(key.clone(), value.clone()))
+        }
+        [item, ..] => Some(EachItem::Single(item.clone())),
+        [] => None,
+    }
+}
+
+/// `PhantomData` mentioning every lifetime and type parameter of the struct,
+/// so the builder stays well-formed even if none of its fields use one.
+fn phantom_type(generics: &syn::Generics) -> Option<proc_macro2::TokenStream> {
+    let lifetimes = generics.lifetimes().map(|param| &param.lifetime);
+    let types = generics.type_params().map(|param| &param.ident);
+    if generics.lifetimes().next().is_none() && generics.type_params().next().is_none() {
+        return None;
+    }
+    Some(quote!(core::marker::PhantomData<(#(&#lifetimes (),)* #(fn() -> #types,)*)>))
+}
+
+/// How a setter argument is turned into the value that gets stored.
+#[derive(Clone, Copy, PartialEq)]
+enum Conversion {
+    /// `value: T`
+    Exact,
+    /// `value: impl Into<T>`
+    Into,
+    /// `value: impl TryInto<T>`, making the setter return a `Result`.
+    TryInto,
+}
+
+/// One argument of a generated setter: `name` ends up holding a `ty`.
+struct SetterArg {
+    name: syn::Ident,
+    ty: proc_macro2::TokenStream,
+    conversion: Conversion,
+}
+
+impl SetterArg {
+    fn new(name: &str, ty: impl quote::ToTokens, conversion: Conversion) -> Self {
+        SetterArg {
+            name: quote::format_ident!("{}", name),
+            ty: quote!(#ty),
+            conversion,
+        }
+    }
+}
+
+/// The parts of a setter signature that depend on its arguments'
+/// conversions.
+struct SetterSignature {
+    generics: Vec<proc_macro2::TokenStream>,
+    params: Vec<proc_macro2::TokenStream>,
+    conversions: Vec<proc_macro2::TokenStream>,
+    /// The error type of a fallible setter.
+    error: Option<proc_macro2::TokenStream>,
+}
+
+impl SetterSignature {
+    fn new(args: &[SetterArg]) -> Self {
+        let mut signature = SetterSignature {
+            generics: Vec::new(),
+            params: Vec::new(),
+            conversions: Vec::new(),
+            error: None,
+        };
+        for SetterArg {
+            name,
+            ty,
+            conversion,
+        } in args
+        {
+            let generic = quote::format_ident!("{}", name.to_string().to_uppercase());
+            match conversion {
+                Conversion::Exact => signature.params.push(quote!(#name:#ty)),
+                Conversion::Into => {
+                    signature
+                        .generics
+                        .push(quote!(#generic:core::convert::Into<#ty>));
+                    signature.params.push(quote!(#name:#generic));
+                    signature
+                        .conversions
+                        .push(quote!(let #name:#ty=core::convert::Into::into(#name);));
+                }
+                Conversion::TryInto => {
+                    signature
+                        .generics
+                        .push(quote!(#generic:core::convert::TryInto<#ty>));
+                    signature.params.push(quote!(#name:#generic));
+                    signature
+                        .conversions
+                        .push(quote!(let #name:#ty=core::convert::TryInto::try_into(#name)?;));
+                    signature.error =
+                        Some(quote!(<#generic as core::convert::TryInto<#ty>>::Error));
+                }
+            }
+        }
+        signature
+    }
+
+    /// The signature of a setter storing one `ty`: through the field's
+    /// `with` closure if it has one, otherwise from a single `value`.
+    fn value(
+        ty: impl quote::ToTokens,
+        conversion: Conversion,
+        with: Option<&syn::ExprClosure>,
+    ) -> Self {
+        let closure = match with {
+            Some(closure) => closure,
+            None => return SetterSignature::new(&[SetterArg::new("value", ty, conversion)]),
+        };
+        let params: Vec<_> = closure.inputs.iter().map(|input| quote!(#input)).collect();
+        let names = closure.inputs.iter().filter_map(|input| match input {
+            syn::Pat::Type(syn::PatType { pat, .. }) => match **pat {
+                syn::Pat::Ident(syn::PatIdent { ref ident, .. }) => Some(ident),
+                _ => None,
+            },
+            _ => None,
+        });
+        let error = match closure.output {
+            syn::ReturnType::Type(_, ref output) => result_error(output),
+            syn::ReturnType::Default => None,
+        };
+        let question = error.as_ref().map(|_| quote!(?));
+        SetterSignature {
+            generics: Vec::new(),
+            params,
+            conversions: vec![quote!(let value:#ty=(#closure)(#(#names),*)#question;)],
+            error: error.map(|error| quote!(#error)),
+        }
+    }
+}
+
+/// How setters and `build()` take the builder.
+#[derive(Clone, Copy, PartialEq, Default)]
+enum Pattern {
+    /// `fn x(&mut self, ..) -> &mut Self`, `build(&self)` clones every field.
+    #[default]
+    Mutable,
+    /// `fn x(self, ..) -> Self`, `build(self)` moves every field out.
+    Owned,
+    /// `fn x(&self, ..) -> Self` returns a modified copy, `build(&self)`
+    /// clones every field.
+    Immutable,
+    /// `const fn x(mut self, ..) -> Self` and `const fn build(self)`, the
+    /// owned pattern usable in constant expressions. Set by
+    /// `#[builder(const)]`.
+    Const,
+}
+
+impl Pattern {
+    fn from_lit(lit_str: &syn::LitStr) -> syn::Result<Self> {
+        match lit_str.value().as_str() {
+            "mutable" => Ok(Pattern::Mutable),
+            "owned" => Ok(Pattern::Owned),
+            "immutable" => Ok(Pattern::Immutable),
+            _ => Err(syn::Error::new_spanned(
+                lit_str,
+                "expected `owned`, `mutable` or `immutable`",
+            )),
+        }
+    }
+
+    /// A setter named `name` taking `args`. `body` receives the expression
+    /// for the builder being modified and returns the statements doing so,
+    /// with every argument already converted to its target type.
+    fn setter(
+        self,
+        vis: &syn::Visibility,
+        name: &syn::Ident,
+        args: &[SetterArg],
+        body: impl FnOnce(&proc_macro2::TokenStream) -> proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        self.setter_signed(vis, name, SetterSignature::new(args), body)
+    }
+
+    /// [`Pattern::setter`] with an already worked out signature.
+    fn setter_signed(
+        self,
+        vis: &syn::Visibility,
+        name: &syn::Ident,
+        signature: SetterSignature,
+        body: impl FnOnce(&proc_macro2::TokenStream) -> proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let SetterSignature {
+            generics,
+            params,
+            conversions,
+            error,
+        } = signature;
+        let (receiver, this, return_type) = match self {
+            Pattern::Mutable => (quote!(&mut self), quote!(self), quote!(&mut Self)),
+            Pattern::Owned | Pattern::Const => (quote!(mut self), quote!(self), quote!(Self)),
+            Pattern::Immutable => (quote!(&self), quote!(new), quote!(Self)),
+        };
+        // The derived Clone of a generic builder may need more of the type
+        // parameters than the struct does, so it is only asked for here.
+        let (copy, bound) = match self {
+            Pattern::Immutable => (
+                Some(quote!(let mut new=core::clone::Clone::clone(self);)),
+                Some(quote!(where Self: core::clone::Clone)),
+            ),
+            Pattern::Mutable | Pattern::Owned | Pattern::Const => (None, None),
+        };
+        let body = body(&this);
+        let (return_type, this) = match error {
+            Some(error) => (
+                quote!(core::result::Result<#return_type, #error>),
+                quote!(core::result::Result::Ok(#this)),
+            ),
+            None => (return_type, this),
+        };
+        let constness = self.constness();
+        quote! {
+            #vis #constness fn #name<#(#generics),*>(#receiver,#(#params),*)->#return_type
+            #bound
+            {
+                #(#conversions)*
+                #copy
+                #body
+                #this
+            }
+        }
+    }
+
+    /// `const` for the functions of a `const` builder.
+    fn constness(self) -> Option<proc_macro2::TokenStream> {
+        match self {
+            Pattern::Const => Some(quote!(const)),
+            Pattern::Mutable | Pattern::Owned | Pattern::Immutable => None,
+        }
+    }
+
+    fn build_receiver(self) -> proc_macro2::TokenStream {
+        match self {
+            Pattern::Owned | Pattern::Const => quote!(self),
+            Pattern::Mutable | Pattern::Immutable => quote!(&self),
+        }
+    }
+
+    /// The `Option` stored in builder field `place`, moved out by an owned
+    /// `build()` and cloned otherwise.
+    fn take(self, place: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        match self {
+            Pattern::Owned | Pattern::Const => place,
+            Pattern::Mutable | Pattern::Immutable => quote!(core::clone::Clone::clone(&#place)),
+        }
+    }
+}
+
+/// `let __default = ...;` for a struct-level `#[builder(default)]`, to be
+/// emitted at the top of `build()` before any [`fallback`] is evaluated.
+fn struct_default(
+    struct_ty: &proc_macro2::TokenStream,
+    options: &StructOptions,
+) -> Option<proc_macro2::TokenStream> {
+    if options.default {
+        Some(quote!(let __default: #struct_ty = core::default::Default::default();))
+    } else {
+        None
+    }
+}
+
+/// The value `build()` uses for a field that was never set, or `None` if the
+/// field is required. Typestate builders keep a plain `Vec` field required
+/// rather than leaving it empty.
+fn fallback(field: &Field, options: &StructOptions) -> Option<proc_macro2::TokenStream> {
+    let Field {
+        member,
+        ty,
+        options: field_options,
+        ..
+    } = field;
+    match field_options.default {
+        Some(FieldDefault::Trait) => Some(quote!(core::default::Default::default())),
+        Some(FieldDefault::Expr(ref expr)) => Some(quote!(#expr)),
+        None if options.default => Some(quote!(__default.#member)),
+        None if field_options.each.is_some()
+            || (is_vec(ty).is_some() && !options.typestate)
+            || field_options.skip
+            || field_options.setter.skip =>
+        {
+            Some(quote!(core::default::Default::default()))
+        }
+        None => None,
+    }
+}
+
+/// The setter of an `Option` field and its `clear_{field}`, alike in both
+/// kinds of builders. The setter takes `inner` if `strip_option`.
+fn option_methods(
+    pattern: Pattern,
+    field: &Field,
+    options: &StructOptions,
+    inner: &syn::Type,
+) -> proc_macro2::TokenStream {
+    let Field {
+        ident,
+        ty,
+        options: field_options,
+        ..
+    } = field;
+    let conversion = field_options.conversion(options);
+    let with = field_options.setter.with.as_ref();
+    let (signature, value) = if field_options.strip_option(options) {
+        let signature = SetterSignature::value(inner, conversion, with);
+        (signature, quote!(core::option::Option::Some(value)))
+    } else {
+        (SetterSignature::value(ty, conversion, with), quote!(value))
+    };
+    let setter_vis = field_options.setter_vis(options);
+    let set_method = pattern.setter_signed(&setter_vis, field.setter_name(), signature, |this| {
+        quote! {#this.#ident=core::option::Option::Some(#value);}
+    });
+    let docs = setter_docs(field, unset_note(field, options));
+    let clear_method = clear_method(pattern, field, options);
+    quote! {
+        #docs
+        #set_method
+        #clear_method
+    }
+}
+
+/// `clear_{field}` with its docs, forgetting what an optional field was set
+/// to.
+fn clear_method(
+    pattern: Pattern,
+    field: &Field,
+    options: &StructOptions,
+) -> proc_macro2::TokenStream {
+    let ident = &field.ident;
+    let setter_vis = field.options.setter_vis(options);
+    let clear_name = clear_name(field.setter_name());
+    let clear_method = pattern.setter(&setter_vis, &clear_name, &[], |this| {
+        quote! {#this.#ident=core::option::Option::None;}
+    });
+    let docs = format!("Unsets `{}`, as if it had never been set.", ident.unraw());
+    quote! {
+        #[doc = #docs]
+        #clear_method
+    }
+}
+
+/// The methods of an `each` field, alike in both kinds of builders: the one
+/// adding an item, `extend_{field}`, the setter assigning the whole collection
+/// unless the item method took its name, and `clear_{field}` if the field is
+/// an `Option` of the collection `optional`.
+fn each_methods(
+    pattern: Pattern,
+    field: &Field,
+    options: &StructOptions,
+    push_method_name: &syn::Ident,
+    item: &EachItem,
+    optional: Option<&syn::Type>,
+) -> proc_macro2::TokenStream {
+    let Field {
+        ident,
+        ty,
+        options: field_options,
+        ..
+    } = field;
+    let conversion = field_options.conversion(options);
+    let setter_vis = field_options.setter_vis(options);
+    let setter_name = field.setter_name();
+    let name = ident.unraw();
+    let collection = optional.unwrap_or(ty);
+    let place =
+        |this: &proc_macro2::TokenStream| EachItem::place(quote!(#this.#ident), optional.is_some());
+    let push_method = pattern.setter(
+        &setter_vis,
+        push_method_name,
+        &item.args(conversion),
+        |this| EachItem::extend(collection, &place(this), &item.once()),
+    );
+    let push_docs = setter_docs(
+        field,
+        &format!("Adds one item to `{}`, which starts out empty.", name),
+    );
+    let extend_method = pattern.setter_signed(
+        &setter_vis,
+        &extend_name(setter_name),
+        item.extend_signature(),
+        |this| EachItem::extend(collection, &place(this), &quote!(items)),
+    );
+    let extend_docs = setter_docs(field, &format!("Adds every item of `items` to `{}`.", name));
+    let set_method = if push_method_name == setter_name {
+        None
+    } else {
+        let (arg, value) = match optional {
+            Some(collection) if field_options.strip_option(options) => (
+                SetterArg::new("value", collection, conversion),
+                quote!(core::option::Option::Some(value)),
+            ),
+            _ => (SetterArg::new("value", ty, conversion), quote!(value)),
+        };
+        let set_method = pattern.setter(&setter_vis, setter_name, &[arg], |this| {
+            quote! {#this.#ident=core::option::Option::Some(#value);}
+        });
+        let docs = setter_docs(field, unset_note(field, options));
+        Some(quote! {
+            #docs
+            #set_method
+        })
+    };
+    let clear_method = optional.map(|_| clear_method(pattern, field, options));
+    quote! {
+        #push_docs
+        #push_method
+        #extend_docs
+        #extend_method
+        #set_method
+        #clear_method
+    }
+}
+
+/// `#[doc]` attributes for a method setting `field`: the field's own docs, or
+/// those given with `#[builder(doc = "...")]`, then `note` as a paragraph of
+/// its own.
+fn setter_docs(field: &Field, note: &str) -> proc_macro2::TokenStream {
+    let docs = match field.options.doc {
+        Some(ref doc) => quote!(#[doc = #doc]),
+        None => {
+            let docs = &field.docs;
+            quote!(#(#docs)*)
+        }
+    };
+    let separator = if docs.is_empty() {
+        None
+    } else {
+        Some(quote!(#[doc = ""]))
+    };
+    quote! {
+        #docs
+        #separator
+        #[doc = #note]
+    }
+}
+
+/// What `build()` does with `field` if it is never set, for its setter's docs.
+fn unset_note(field: &Field, options: &StructOptions) -> &'static str {
+    if field.options.default_with.is_some() {
+        return "Optional: `build()` works out its default from the rest of the builder if it \
+                is never set.";
+    }
+    match (&field.kind, fallback(field, options)) {
+        (_, Some(_)) => "Optional: `build()` uses the field's default if it is never set.",
+        (FieldKind::Optional(_), None) => {
+            "Optional: `build()` leaves it `None` if it is never set."
+        }
+        (FieldKind::SubBuilder(_), None) => {
+            "If it is never set, `build()` builds it from an empty builder."
+        }
+        (_, None) if options.typestate => "Required: `build()` is only available once it is set.",
+        (_, None) => "Required: `build()` fails if it is never set.",
+    }
+}
+
+/// The variant of the generated field status enum describing `field`, after
+/// what `build()` does if it is never set.
+fn field_status(field: &Field, options: &StructOptions) -> &'static str {
+    if field.options.default_with.is_some() {
+        return "Defaulted";
+    }
+    match (&field.kind, fallback(field, options)) {
+        (FieldKind::Each(..), _) => "Each",
+        (_, Some(_)) => "Defaulted",
+        (FieldKind::Optional(_), None) => "Optional",
+        (FieldKind::SubBuilder(_), None) => "SubBuilder",
+        (_, None) => "Required",
+    }
+}
+
+/// A field's type as written, for `FIELDS`. Tokens print with spaces around
+/// punctuation, which are taken out again where rustfmt would not have them.
+fn type_name(ty: &syn::Type) -> String {
+    let mut name = quote!(#ty).to_string();
+    for (spaced, tight) in &[
+        (" :: ", "::"),
+        (":: ", "::"),
+        (" < ", "<"),
+        (" <", "<"),
+        ("< ", "<"),
+        (" >", ">"),
+        (" ,", ","),
+        (" ;", ";"),
+        ("& ", "&"),
+        ("[ ", "["),
+        (" ]", "]"),
+        ("( ", "("),
+        (" )", ")"),
+    ] {
+        name = name.replace(spaced, tight);
+    }
+    name
+}
+
+/// `Message::Connect` for doc links, without the spaces of its tokens.
+fn doc_path(path: &impl quote::ToTokens) -> String {
+    quote!(#path).to_string().replace(' ', "")
 }
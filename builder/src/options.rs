@@ -0,0 +1,963 @@
+//! Parsing of the inert `#[builder(...)]` attributes. Every problem is
+//! collected into [`Errors`] instead of stopping at the first, so the caller
+//! sees all of them in one compile.
+
+use quote::ToTokens;
+
+use crate::{is_result, Conversion, Pattern};
+
+/// Errors found so far, reported together as one `syn::Error`.
+#[derive(Default)]
+pub struct Errors(Option<syn::Error>);
+
+impl Errors {
+    pub fn push(&mut self, error: syn::Error) {
+        match self.0 {
+            Some(ref mut errors) => errors.combine(error),
+            None => self.0 = Some(error),
+        }
+    }
+
+    /// Records the error of `result`, if any, and returns its value.
+    pub fn ok<T>(&mut self, result: syn::Result<T>) -> Option<T> {
+        result.map_err(|error| self.push(error)).ok()
+    }
+
+    pub fn finish(self) -> syn::Result<()> {
+        match self.0 {
+            Some(errors) => Err(errors),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Options given through `#[builder(...)]` on the struct itself.
+#[derive(Default)]
+pub struct StructOptions {
+    /// `#[builder(typestate)]`
+    pub typestate: bool,
+    /// `#[builder(error = "MyError")]`, a type implementing
+    /// `From<{Struct}BuilderError>` that `build()` returns instead.
+    pub error: Option<syn::Path>,
+    /// `#[builder(default)]`, take unset fields from the struct's own
+    /// `Default` impl.
+    pub default: bool,
+    /// `#[builder(pattern = "...")]`
+    pub pattern: Pattern,
+    /// `#[builder(setter(...))]`
+    pub setter: SetterOptions,
+    /// `#[builder(build_fn(...))]`
+    pub build_fn: BuildFnOptions,
+    /// `#[builder(name = "...")]`, replacing `{Struct}Builder`.
+    pub name: Option<syn::Ident>,
+    /// `#[builder(vis = "...")]` of the builder, its error type, `builder()`,
+    /// `build()` and, unless overridden, the setters. Private by default.
+    pub vis: Option<syn::Visibility>,
+    /// `#[builder(derive(...))]`, added to the builder's own derives.
+    pub derive: Vec<syn::Path>,
+    /// `#[builder(serde)]`, deriving `serde::Deserialize` for the builder.
+    /// Needs the `serde` feature.
+    pub serde: bool,
+    /// `#[builder(no_std)]`, for crates without `std`: the error type holds
+    /// `alloc::string::String` and does not implement `std::error::Error`.
+    pub no_std: bool,
+    /// `#[builder(doc = "...")]`, replacing the builder's generated docs.
+    pub doc: Option<syn::LitStr>,
+    /// `#[builder(one_of(...))]`, `at_most_one_of(...)` and `requires(...)`
+    pub groups: Vec<FieldGroup>,
+    /// `#[builder(const)]`, making `builder()`, the setters and `build()`
+    /// `const fn`s. Implies the owned pattern.
+    pub const_fn: bool,
+}
+
+impl StructOptions {
+    /// Visibility of the builder and everything generated alongside it.
+    pub fn vis(&self) -> syn::Visibility {
+        self.vis.clone().unwrap_or(syn::Visibility::Inherited)
+    }
+
+    /// Name of the finishing method, `build` unless renamed.
+    pub fn build_fn_name(&self) -> syn::Ident {
+        self.build_fn
+            .name
+            .clone()
+            .unwrap_or_else(|| quote::format_ident!("build"))
+    }
+
+    pub fn from_attrs(attrs: &[syn::Attribute], errors: &mut Errors) -> Self {
+        let mut options = StructOptions::default();
+        let mut typestate = None;
+        let mut default = None;
+        let mut pattern = None;
+        let mut derive = None;
+        let mut serde = None;
+        let mut no_std = None;
+        let mut const_fn = None;
+        for meta in builder_metas(attrs, errors) {
+            match meta {
+                syn::NestedMeta::Meta(syn::Meta::Path(ref path)) if path.is_ident("const") => {
+                    set_once(&mut const_fn, path.clone(), path, errors);
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(ref path)) if path.is_ident("no_std") => {
+                    set_once(&mut no_std, (), path, errors);
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(ref path)) if path.is_ident("serde") => {
+                    if !cfg!(feature = "serde") {
+                        errors.push(syn::Error::new_spanned(
+                            path,
+                            "`serde` needs the `serde` feature of derive_builder",
+                        ));
+                    }
+                    set_once(&mut serde, (), path, errors);
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(ref path)) if path.is_ident("typestate") => {
+                    set_once(&mut typestate, (), path, errors);
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(ref path)) if path.is_ident("default") => {
+                    set_once(&mut default, (), path, errors);
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(ref name_value))
+                    if name_value.path.is_ident("error") =>
+                {
+                    if let Some(error) = errors.ok(parse_lit_str(name_value)) {
+                        set_once(&mut options.error, error, &name_value.path, errors);
+                    }
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(ref name_value))
+                    if name_value.path.is_ident("pattern") =>
+                {
+                    let value = errors.ok(lit_str(name_value).and_then(Pattern::from_lit));
+                    if let Some(value) = value {
+                        set_once(&mut pattern, value, &name_value.path, errors);
+                    }
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(ref name_value))
+                    if name_value.path.is_ident("name") =>
+                {
+                    if let Some(name) = errors.ok(parse_lit_str(name_value)) {
+                        set_once(&mut options.name, name, &name_value.path, errors);
+                    }
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(ref name_value))
+                    if name_value.path.is_ident("doc") =>
+                {
+                    if let Some(doc) = errors.ok(lit_str(name_value)) {
+                        set_once(&mut options.doc, doc.clone(), &name_value.path, errors);
+                    }
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(ref name_value))
+                    if name_value.path.is_ident("vis") =>
+                {
+                    if let Some(vis) = errors.ok(parse_lit_str(name_value)) {
+                        set_once(&mut options.vis, vis, &name_value.path, errors);
+                    }
+                }
+                syn::NestedMeta::Meta(syn::Meta::List(ref list)) if list.path.is_ident("derive") => {
+                    set_once(&mut derive, (), &list.path, errors);
+                    for nested in &list.nested {
+                        match nested {
+                            syn::NestedMeta::Meta(syn::Meta::Path(path)) => {
+                                options.derive.push(path.clone());
+                            }
+                            _ => errors.push(syn::Error::new_spanned(
+                                nested,
+                                "expected a trait to derive, as in `derive(Debug, Clone)`",
+                            )),
+                        }
+                    }
+                }
+                syn::NestedMeta::Meta(syn::Meta::List(ref list)) if list.path.is_ident("one_of") => {
+                    options.groups.extend(errors.ok(FieldGroup::parse(GroupKind::OneOf, list)));
+                }
+                syn::NestedMeta::Meta(syn::Meta::List(ref list))
+                    if list.path.is_ident("at_most_one_of") =>
+                {
+                    let group = FieldGroup::parse(GroupKind::AtMostOneOf, list);
+                    options.groups.extend(errors.ok(group));
+                }
+                syn::NestedMeta::Meta(syn::Meta::List(ref list))
+                    if list.path.is_ident("requires") =>
+                {
+                    let group = FieldGroup::parse(GroupKind::Requires, list);
+                    options.groups.extend(errors.ok(group));
+                }
+                syn::NestedMeta::Meta(syn::Meta::List(ref list)) if list.path.is_ident("setter") => {
+                    options.setter.parse(list, errors);
+                    if let Some(ref name) = options.setter.name {
+                        errors.push(syn::Error::new_spanned(
+                            name,
+                            "`setter(name = \"...\")` can only be given on a field",
+                        ));
+                    }
+                    if options.setter.skip {
+                        errors.push(syn::Error::new_spanned(
+                            list,
+                            "`setter(skip)` can only be given on a field",
+                        ));
+                    }
+                    if let Some(ref custom) = options.setter.custom {
+                        errors.push(syn::Error::new_spanned(
+                            custom,
+                            "`setter(custom)` can only be given on a field",
+                        ));
+                    }
+                    if let Some(ref with) = options.setter.with {
+                        errors.push(syn::Error::new_spanned(
+                            with,
+                            "`setter(with = \"...\")` can only be given on a field",
+                        ));
+                    }
+                }
+                syn::NestedMeta::Meta(syn::Meta::List(ref list))
+                    if list.path.is_ident("build_fn") =>
+                {
+                    options.build_fn.parse(list, errors);
+                }
+                _ => errors.push(unknown(
+                    &meta,
+                    "`typestate`, `const`, `default`, `serde`, `no_std`, `error = \"...\"`, `pattern = \"...\"`, `name = \"...\"`, `vis = \"...\"`, `doc = \"...\"`, `derive(...)`, `one_of(...)`, `at_most_one_of(...)`, `requires(...)`, `setter(...)` or `build_fn(...)`",
+                )),
+            }
+        }
+        options.typestate = typestate.is_some();
+        options.serde = serde.is_some();
+        options.no_std = no_std.is_some();
+        options.default = default.is_some();
+        options.pattern = pattern.unwrap_or_default();
+
+        if let Some(const_fn) = const_fn {
+            options.const_fn = true;
+            options.pattern = Pattern::Const;
+            if let Some(Pattern::Mutable) | Some(Pattern::Immutable) = pattern {
+                errors.push(syn::Error::new_spanned(
+                    &const_fn,
+                    "a `const` builder always uses the owned pattern",
+                ));
+            }
+            if typestate.is_some() {
+                errors.push(syn::Error::new_spanned(
+                    &const_fn,
+                    "`const` cannot be combined with `typestate`",
+                ));
+            }
+            if options.default {
+                errors.push(syn::Error::new_spanned(
+                    &const_fn,
+                    "`default` calls `Default::default()`, which is not a `const fn`",
+                ));
+            }
+            if let Some(ref error) = options.error {
+                errors.push(syn::Error::new_spanned(
+                    error,
+                    "`error` converts through `From::from`, which is not a `const fn`",
+                ));
+            }
+            if let Some(ref validate) = options.build_fn.validate {
+                errors.push(syn::Error::new_spanned(
+                    validate,
+                    "a `const` builder cannot call validators",
+                ));
+            }
+            if options.setter.conversion.is_some() {
+                errors.push(syn::Error::new_spanned(
+                    &const_fn,
+                    "`into` and `try_into` setters cannot be `const fn`s",
+                ));
+            }
+            for group in &options.groups {
+                errors.push(syn::Error::new_spanned(
+                    &group.path,
+                    "field groups are not supported by `const` builders",
+                ));
+            }
+        }
+
+        if options.typestate {
+            if let Some(Pattern::Mutable) | Some(Pattern::Immutable) = pattern {
+                errors.push(syn::Error::new(
+                    proc_macro2::Span::call_site(),
+                    "a typestate builder always uses the owned pattern",
+                ));
+            }
+            if let Some(ref validate) = options.build_fn.validate {
+                errors.push(syn::Error::new_spanned(
+                    validate,
+                    "a typestate builder cannot be validated because its `build()` cannot fail",
+                ));
+            }
+            if let Some(ref error) = options.error {
+                errors.push(syn::Error::new_spanned(
+                    error,
+                    "`error` has no effect on a typestate builder, whose `build()` cannot fail",
+                ));
+            }
+            if options.serde {
+                errors.push(syn::Error::new(
+                    proc_macro2::Span::call_site(),
+                    "a typestate builder cannot be deserialized, as its type depends on which fields are set",
+                ));
+            }
+            for group in &options.groups {
+                errors.push(syn::Error::new_spanned(
+                    &group.path,
+                    "field groups are not supported by typestate builders, as their `build()` cannot fail",
+                ));
+            }
+        }
+        options
+    }
+}
+
+/// A constraint on which of the listed fields are set, checked at the start
+/// of `build()`.
+pub struct FieldGroup {
+    pub kind: GroupKind,
+    pub path: syn::Path,
+    pub fields: Vec<syn::Ident>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum GroupKind {
+    /// `one_of(...)`: exactly one of the fields is set.
+    OneOf,
+    /// `at_most_one_of(...)`: no more than one of the fields is set.
+    AtMostOneOf,
+    /// `requires(...)`: either all of the fields are set, or none.
+    Requires,
+}
+
+impl FieldGroup {
+    fn parse(kind: GroupKind, list: &syn::MetaList) -> syn::Result<Self> {
+        let fields = list
+            .nested
+            .iter()
+            .map(|nested| {
+                match nested {
+                    syn::NestedMeta::Meta(syn::Meta::Path(path)) => path.get_ident().cloned(),
+                    _ => None,
+                }
+                .ok_or_else(|| syn::Error::new_spanned(nested, "expected a field name"))
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+        if fields.len() < 2 {
+            return Err(syn::Error::new_spanned(
+                list,
+                "a field group needs at least two fields",
+            ));
+        }
+        Ok(FieldGroup {
+            kind,
+            path: list.path.clone(),
+            fields,
+        })
+    }
+}
+
+/// `#[builder(build_fn(...))]` on the struct.
+#[derive(Default)]
+pub struct BuildFnOptions {
+    /// `validate = "path"`, a `fn(&{Struct}Builder) -> Result<(), String>`
+    /// called before any field is read.
+    pub validate: Option<syn::Path>,
+    /// `name = "..."`, replacing `build`.
+    pub name: Option<syn::Ident>,
+}
+
+impl BuildFnOptions {
+    fn parse(&mut self, list: &syn::MetaList, errors: &mut Errors) {
+        for meta in &list.nested {
+            match meta {
+                syn::NestedMeta::Meta(syn::Meta::NameValue(name_value))
+                    if name_value.path.is_ident("validate") =>
+                {
+                    if let Some(validate) = errors.ok(parse_lit_str(name_value)) {
+                        set_once(&mut self.validate, validate, &name_value.path, errors);
+                    }
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(name_value))
+                    if name_value.path.is_ident("name") =>
+                {
+                    if let Some(name) = errors.ok(parse_lit_str(name_value)) {
+                        set_once(&mut self.name, name, &name_value.path, errors);
+                    }
+                }
+                _ => errors.push(unknown(meta, "`validate = \"...\"` or `name = \"...\"`")),
+            }
+        }
+    }
+}
+
+/// `#[builder(setter(...))]`, on a field or as the default for all fields
+/// when given on the struct.
+#[derive(Default)]
+pub struct SetterOptions {
+    /// `into` or `try_into`
+    pub conversion: Option<Conversion>,
+    /// `name = "..."`, replacing the field's name. Only valid on a field.
+    pub name: Option<syn::Ident>,
+    /// `vis = "..."`, replacing the builder's visibility.
+    pub vis: Option<syn::Visibility>,
+    /// `skip`: keep the field in the builder, but generate no setter for it.
+    /// Only valid on a field.
+    pub skip: bool,
+    /// `strip_option = false` makes the setter of an `Option<T>` field take
+    /// `Option<T>` rather than `T`.
+    pub strip_option: Option<syn::LitBool>,
+    /// `custom`: keep the field in the builder and `build()`, but leave its
+    /// setter to a hand-written `impl`. Only valid on a field.
+    pub custom: Option<syn::Path>,
+    /// `with = "|a: A, ..| expr"`, a closure turning the setter's arguments
+    /// into the stored value, or `with = "path::to_fn as fn(A, ..) -> T"`,
+    /// kept as the closure calling the function. It makes the setter fallible
+    /// if it returns a `Result`. Only valid on a field.
+    pub with: Option<syn::ExprClosure>,
+}
+
+impl SetterOptions {
+    fn parse(&mut self, list: &syn::MetaList, errors: &mut Errors) {
+        let mut skip = None;
+        for meta in &list.nested {
+            match meta {
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("skip") => {
+                    set_once(&mut skip, path.clone(), path, errors);
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("custom") => {
+                    set_once(&mut self.custom, path.clone(), path, errors);
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(name_value))
+                    if name_value.path.is_ident("with") =>
+                {
+                    if let Some(with) = errors.ok(parse_with(name_value)) {
+                        set_once(&mut self.with, with, &name_value.path, errors);
+                    }
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("into") => {
+                    set_once(&mut self.conversion, Conversion::Into, path, errors);
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("try_into") => {
+                    set_once(&mut self.conversion, Conversion::TryInto, path, errors);
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(name_value))
+                    if name_value.path.is_ident("name") =>
+                {
+                    if let Some(name) = errors.ok(parse_lit_str(name_value)) {
+                        set_once(&mut self.name, name, &name_value.path, errors);
+                    }
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(name_value))
+                    if name_value.path.is_ident("vis") =>
+                {
+                    if let Some(vis) = errors.ok(parse_lit_str(name_value)) {
+                        set_once(&mut self.vis, vis, &name_value.path, errors);
+                    }
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("strip_option") => {
+                    let value = syn::LitBool {
+                        value: true,
+                        span: syn::spanned::Spanned::span(path),
+                    };
+                    set_once(&mut self.strip_option, value, path, errors);
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(name_value))
+                    if name_value.path.is_ident("strip_option") =>
+                {
+                    match name_value.lit {
+                        syn::Lit::Bool(ref value) => {
+                            let value = value.clone();
+                            set_once(&mut self.strip_option, value, &name_value.path, errors);
+                        }
+                        ref lit => errors.push(syn::Error::new_spanned(
+                            lit,
+                            "expected `strip_option = true` or `strip_option = false`",
+                        )),
+                    }
+                }
+                _ => errors.push(unknown(
+                    meta,
+                    "`skip`, `custom`, `into`, `try_into`, `strip_option = false`, `with = \"...\"`, `name = \"...\"` or `vis = \"...\"`",
+                )),
+            }
+        }
+        if let Some(skip) = skip {
+            self.skip = true;
+            if self.conversion.is_some()
+                || self.name.is_some()
+                || self.vis.is_some()
+                || self.strip_option.is_some()
+                || self.custom.is_some()
+                || self.with.is_some()
+            {
+                errors.push(syn::Error::new_spanned(
+                    skip,
+                    "a skipped setter cannot be given other setter options",
+                ));
+            }
+        } else if let Some(ref custom) = self.custom {
+            if self.conversion.is_some()
+                || self.name.is_some()
+                || self.vis.is_some()
+                || self.strip_option.is_some()
+                || self.with.is_some()
+            {
+                errors.push(syn::Error::new_spanned(
+                    custom,
+                    "a custom setter is written by hand, so it cannot be given other setter options",
+                ));
+            }
+        }
+        if let (Some(with), Some(_)) = (&self.with, self.conversion) {
+            errors.push(syn::Error::new_spanned(
+                with,
+                "`with` already converts the setter's arguments, so it cannot be combined with `into` or `try_into`",
+            ));
+        }
+    }
+}
+
+/// Options given through `#[builder(...)]` on a field.
+#[derive(Default)]
+pub struct FieldOptions {
+    /// `#[builder(each = "...")]`
+    pub each: Option<syn::Ident>,
+    /// `#[builder(default)]` or `#[builder(default = "...")]`
+    pub default: Option<FieldDefault>,
+    /// `#[builder(default_with = "|builder| ...")]`, a closure working out
+    /// the default from the rest of the builder. Its dependencies on other
+    /// `default_with` fields are found from `builder.field` reads; any other
+    /// use of the builder counts as reading every field.
+    pub default_with: Option<syn::ExprClosure>,
+    /// `#[builder(setter(...))]`
+    pub setter: SetterOptions,
+    /// `#[builder(validate = "path")]`, a `fn(&T) -> Result<(), String>`
+    /// called on the field's final value.
+    pub validate: Option<syn::Path>,
+    /// `#[builder(name = "...")]`, mostly for naming tuple fields.
+    pub name: Option<syn::Ident>,
+    /// `#[builder(skip)]`, leaving the field out of the builder altogether.
+    pub skip: bool,
+    /// `#[builder(field(attrs(...)))]`, attributes put on the field's slot in
+    /// the builder, such as `serde(rename = "...")`.
+    pub attrs: Vec<syn::Meta>,
+    /// `#[builder(sub_builder)]`
+    pub sub_builder: Option<SubBuilder>,
+    /// `#[builder(doc = "...")]`, replacing the field's own docs on its
+    /// setters.
+    pub doc: Option<syn::LitStr>,
+}
+
+/// `#[builder(sub_builder)]` on a field whose type derives `Builder` too.
+pub struct SubBuilder {
+    pub path: syn::Path,
+    /// `sub_builder = "..."`, the field type's builder when it isn't named
+    /// `{Type}Builder`.
+    pub builder: Option<syn::Type>,
+}
+
+pub enum FieldDefault {
+    /// `Default::default()`
+    Trait,
+    /// An expression evaluated in `build()` when the field is unset.
+    Expr(Box<syn::Expr>),
+}
+
+impl FieldOptions {
+    /// How this field's setters convert their arguments.
+    pub fn conversion(&self, options: &StructOptions) -> Conversion {
+        self.setter
+            .conversion
+            .or(options.setter.conversion)
+            .unwrap_or(Conversion::Exact)
+    }
+
+    /// Whether the setter of an `Option<T>` field takes `T`.
+    pub fn strip_option(&self, options: &StructOptions) -> bool {
+        self.setter
+            .strip_option
+            .as_ref()
+            .or(options.setter.strip_option.as_ref())
+            .is_none_or(|strip_option| strip_option.value)
+    }
+
+    /// Visibility of this field's setters.
+    pub fn setter_vis(&self, options: &StructOptions) -> syn::Visibility {
+        self.setter
+            .vis
+            .as_ref()
+            .or(options.setter.vis.as_ref())
+            .map_or_else(|| options.vis(), Clone::clone)
+    }
+
+    pub fn from_attrs(attrs: &[syn::Attribute], errors: &mut Errors) -> Self {
+        let mut options = FieldOptions::default();
+        let mut skip = None;
+        for attr in attrs {
+            if !attr.path.is_ident("builder") {
+                continue;
+            }
+            let attr_body = match errors.ok(attr.parse_meta()) {
+                Some(attr_body) => attr_body,
+                None => continue,
+            };
+            let nested = match attr_body {
+                syn::Meta::List(syn::MetaList { ref nested, .. }) => nested,
+                _ => {
+                    errors.push(syn::Error::new_spanned(
+                        &attr_body,
+                        "expected `builder(...)`",
+                    ));
+                    continue;
+                }
+            };
+            for meta in nested {
+                match meta {
+                    syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("default") => {
+                        set_once(&mut options.default, FieldDefault::Trait, path, errors);
+                    }
+                    syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("skip") => {
+                        set_once(&mut skip, path.clone(), path, errors);
+                    }
+                    syn::NestedMeta::Meta(syn::Meta::Path(path))
+                        if path.is_ident("sub_builder") =>
+                    {
+                        let sub_builder = SubBuilder {
+                            path: path.clone(),
+                            builder: None,
+                        };
+                        set_once(&mut options.sub_builder, sub_builder, path, errors);
+                    }
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(name_value))
+                        if name_value.path.is_ident("sub_builder") =>
+                    {
+                        if let Some(builder) = errors.ok(parse_lit_str(name_value)) {
+                            let sub_builder = SubBuilder {
+                                path: name_value.path.clone(),
+                                builder: Some(builder),
+                            };
+                            set_once(
+                                &mut options.sub_builder,
+                                sub_builder,
+                                &name_value.path,
+                                errors,
+                            );
+                        }
+                    }
+                    syn::NestedMeta::Meta(syn::Meta::List(list))
+                        if list.path.is_ident("setter") =>
+                    {
+                        options.setter.parse(list, errors);
+                    }
+                    syn::NestedMeta::Meta(syn::Meta::List(list)) if list.path.is_ident("field") => {
+                        options.parse_field(list, errors);
+                    }
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(name_value))
+                        if name_value.path.is_ident("each") =>
+                    {
+                        if let Some(each) = errors.ok(parse_lit_str(name_value)) {
+                            set_once(&mut options.each, each, &name_value.path, errors);
+                        }
+                    }
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(name_value))
+                        if name_value.path.is_ident("default") =>
+                    {
+                        if let Some(expr) = errors.ok(parse_lit_str(name_value)) {
+                            let default = FieldDefault::Expr(Box::new(expr));
+                            set_once(&mut options.default, default, &name_value.path, errors);
+                        }
+                    }
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(name_value))
+                        if name_value.path.is_ident("default_with") =>
+                    {
+                        if let Some(closure) = errors.ok(parse_default_with(name_value)) {
+                            set_once(&mut options.default_with, closure, &name_value.path, errors);
+                        }
+                    }
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(name_value))
+                        if name_value.path.is_ident("doc") =>
+                    {
+                        if let Some(doc) = errors.ok(lit_str(name_value)) {
+                            set_once(&mut options.doc, doc.clone(), &name_value.path, errors);
+                        }
+                    }
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(name_value))
+                        if name_value.path.is_ident("validate") =>
+                    {
+                        if let Some(validate) = errors.ok(parse_lit_str(name_value)) {
+                            set_once(&mut options.validate, validate, &name_value.path, errors);
+                        }
+                    }
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(name_value))
+                        if name_value.path.is_ident("name") =>
+                    {
+                        if let Some(name) = errors.ok(parse_lit_str(name_value)) {
+                            set_once(&mut options.name, name, &name_value.path, errors);
+                        }
+                    }
+                    _ => errors.push(unknown(
+                        meta,
+                        "`default`, `skip`, `sub_builder`, `each = \"...\"`, `default = \"...\"`, `default_with = \"...\"`, `sub_builder = \"...\"`, `doc = \"...\"`, `validate = \"...\"`, `name = \"...\"`, `setter(...)` or `field(...)`",
+                    )),
+                }
+            }
+        }
+        if let (Some(default_with), Some(_)) = (&options.default_with, &options.default) {
+            errors.push(syn::Error::new_spanned(
+                default_with,
+                "`default_with` cannot be combined with `default`",
+            ));
+        }
+        if let Some(skip) = skip {
+            options.skip = true;
+            let setter = &options.setter;
+            if options.each.is_some()
+                || options.validate.is_some()
+                || setter.skip
+                || setter.conversion.is_some()
+                || setter.name.is_some()
+                || setter.vis.is_some()
+                || setter.strip_option.is_some()
+                || setter.custom.is_some()
+                || setter.with.is_some()
+                || options.sub_builder.is_some()
+                || options.doc.is_some()
+                || options.default_with.is_some()
+                || !options.attrs.is_empty()
+            {
+                errors.push(syn::Error::new_spanned(
+                    skip,
+                    "a skipped field only accepts `default`, as it has no setter",
+                ));
+            }
+        } else if let (true, Some(each)) = (options.setter.skip, &options.each) {
+            errors.push(syn::Error::new_spanned(
+                each,
+                "`each` has no effect on a field whose setter is skipped",
+            ));
+        } else if let (Some(_), Some(each)) = (&options.setter.custom, &options.each) {
+            errors.push(syn::Error::new_spanned(
+                each,
+                "`each` has no effect on a field whose setter is custom",
+            ));
+        } else if let (Some(with), Some(_)) = (&options.setter.with, &options.each) {
+            errors.push(syn::Error::new_spanned(
+                with,
+                "`with` cannot be combined with `each`",
+            ));
+        } else if let (Some(sub_builder), Some(_)) = (&options.sub_builder, &options.each) {
+            errors.push(syn::Error::new_spanned(
+                &sub_builder.path,
+                "`sub_builder` cannot be combined with `each`",
+            ));
+        }
+        options
+    }
+
+    /// `field(attrs(...))`
+    fn parse_field(&mut self, list: &syn::MetaList, errors: &mut Errors) {
+        for meta in &list.nested {
+            match meta {
+                syn::NestedMeta::Meta(syn::Meta::List(attrs)) if attrs.path.is_ident("attrs") => {
+                    for attr in &attrs.nested {
+                        match attr {
+                            syn::NestedMeta::Meta(attr) => self.attrs.push(attr.clone()),
+                            syn::NestedMeta::Lit(lit) => errors.push(syn::Error::new_spanned(
+                                lit,
+                                "expected an attribute, as in `attrs(serde(rename = \"...\"))`",
+                            )),
+                        }
+                    }
+                }
+                _ => errors.push(unknown(meta, "`attrs(...)`")),
+            }
+        }
+    }
+}
+
+/// The contents of every `#[builder(...)]` among `attrs`.
+fn builder_metas(attrs: &[syn::Attribute], errors: &mut Errors) -> Vec<syn::NestedMeta> {
+    let mut metas = Vec::new();
+    for attr in attrs {
+        if !attr.path.is_ident("builder") {
+            continue;
+        }
+        match errors.ok(attr.parse_meta()) {
+            Some(syn::Meta::List(list)) => metas.extend(list.nested),
+            Some(attr_body) => errors.push(syn::Error::new_spanned(
+                attr_body,
+                "expected `builder(...)`",
+            )),
+            None => {}
+        }
+    }
+    metas
+}
+
+/// Stores `value` unless the option was already given.
+fn set_once<T>(slot: &mut Option<T>, value: T, key: &syn::Path, errors: &mut Errors) {
+    if slot.is_some() {
+        let message = format!("duplicate `{}`", key.to_token_stream());
+        errors.push(syn::Error::new_spanned(key, message));
+    } else {
+        *slot = Some(value);
+    }
+}
+
+/// The string literal of `key = "..."`.
+fn lit_str(name_value: &syn::MetaNameValue) -> syn::Result<&syn::LitStr> {
+    match name_value.lit {
+        syn::Lit::Str(ref lit_str) => Ok(lit_str),
+        ref lit => Err(syn::Error::new_spanned(
+            lit,
+            format!(
+                "expected a string, as in `{} = \"...\"`",
+                name_value.path.to_token_stream()
+            ),
+        )),
+    }
+}
+
+/// Parses the contents of the string in `key = "..."`.
+fn parse_lit_str<T: syn::parse::Parse>(name_value: &syn::MetaNameValue) -> syn::Result<T> {
+    lit_str(name_value)?.parse()
+}
+
+/// Parses `default_with = "..."`, which must be a closure taking the builder
+/// as its only parameter, bound to a name.
+fn parse_default_with(name_value: &syn::MetaNameValue) -> syn::Result<syn::ExprClosure> {
+    let lit = lit_str(name_value)?;
+    match lit.parse()? {
+        syn::Expr::Closure(closure)
+            if closure.inputs.len() == 1 && default_with_param(&closure).is_some() =>
+        {
+            Ok(closure)
+        }
+        _ => Err(syn::Error::new_spanned(
+            lit,
+            "expected a closure taking the builder, as in `default_with = \"|builder| ...\"`",
+        )),
+    }
+}
+
+/// The name a `default_with` closure gives the builder.
+pub fn default_with_param(closure: &syn::ExprClosure) -> Option<&syn::Ident> {
+    let pat = match closure.inputs.first()? {
+        syn::Pat::Type(syn::PatType { pat, .. }) => pat,
+        pat => pat,
+    };
+    match pat {
+        syn::Pat::Ident(syn::PatIdent {
+            ident,
+            by_ref: None,
+            subpat: None,
+            ..
+        }) => Some(ident),
+        _ => None,
+    }
+}
+
+/// Parses `with = "..."`, which must be a closure naming and typing each of
+/// its parameters, as those become the setter's, or a function path cast to
+/// its signature, as in `path::to_fn as fn(&str) -> u16`. The latter is taken
+/// as the closure calling the function with the setter's arguments.
+fn parse_with(name_value: &syn::MetaNameValue) -> syn::Result<syn::ExprClosure> {
+    let lit = lit_str(name_value)?;
+    let closure = match lit.parse()? {
+        syn::Expr::Closure(closure) => closure,
+        syn::Expr::Cast(syn::ExprCast { expr, ty, .. }) => match (*expr, *ty) {
+            (syn::Expr::Path(path), syn::Type::BareFn(signature)) => {
+                with_function(lit, &path, &signature)?
+            }
+            (_, ty) => {
+                return Err(syn::Error::new_spanned(
+                    ty,
+                    "expected a function signature, as in `fn(&str) -> u16`",
+                ))
+            }
+        },
+        syn::Expr::Path(_) => {
+            return Err(syn::Error::new_spanned(
+                lit,
+                "a function needs its signature to be usable as a setter, as in \
+                 `with = \"path::to_fn as fn(&str) -> u16\"`",
+            ))
+        }
+        _ => {
+            return Err(syn::Error::new_spanned(
+                lit,
+                "expected a closure or a function, as in `with = \"|value: &str| \
+                 path::to_fn(value)\"` or `with = \"path::to_fn as fn(&str) -> u16\"`",
+            ))
+        }
+    };
+    for input in &closure.inputs {
+        match input {
+            syn::Pat::Type(syn::PatType { pat, .. })
+                if matches!(
+                    **pat,
+                    syn::Pat::Ident(syn::PatIdent {
+                        by_ref: None,
+                        subpat: None,
+                        ..
+                    })
+                ) => {}
+            _ => return Err(syn::Error::new_spanned(
+                input,
+                "each parameter of a `with` closure needs a name and a type, as in `value: &str`",
+            )),
+        }
+    }
+    if let syn::ReturnType::Type(_, ref output) = closure.output {
+        if let (Some(_), None) = (is_result(output), crate::result_error(output)) {
+            return Err(syn::Error::new_spanned(
+                output,
+                "a fallible `with` closure must spell out its error type, as in `Result<T, E>`",
+            ));
+        }
+    }
+    Ok(closure)
+}
+
+/// The closure calling `function`, whose parameters are named after those of
+/// `signature`, or `value`, `value1`, ... where it leaves them unnamed.
+fn with_function(
+    lit: &syn::LitStr,
+    function: &syn::ExprPath,
+    signature: &syn::TypeBareFn,
+) -> syn::Result<syn::ExprClosure> {
+    if let Some(ref variadic) = signature.variadic {
+        return Err(syn::Error::new_spanned(
+            variadic,
+            "a `with` function cannot be variadic",
+        ));
+    }
+    let names: Vec<syn::Ident> = signature
+        .inputs
+        .iter()
+        .enumerate()
+        .map(|(i, input)| match input.name {
+            Some((ref name, _)) => name.clone(),
+            None if i == 0 => syn::Ident::new("value", lit.span()),
+            None => quote::format_ident!("value{}", i, span = lit.span()),
+        })
+        .collect();
+    let types = signature.inputs.iter().map(|input| &input.ty);
+    let output = &signature.output;
+    syn::parse2(quote::quote_spanned! {lit.span()=>
+        |#(#names: #types),*| #output { #function(#(#names),*) }
+    })
+}
+
+fn unknown(meta: &syn::NestedMeta, expected: &str) -> syn::Error {
+    let message = match meta {
+        syn::NestedMeta::Meta(meta) => format!(
+            "unknown builder option `{}`, expected {}",
+            meta.path().to_token_stream(),
+            expected
+        ),
+        syn::NestedMeta::Lit(_) => format!("expected {}", expected),
+    };
+    let span: &dyn ToTokens = match meta {
+        syn::NestedMeta::Meta(meta) => meta.path(),
+        syn::NestedMeta::Lit(lit) => lit,
+    };
+    syn::Error::new_spanned(span, message)
+}
@@ -0,0 +1,354 @@
+//! `#[builder(typestate)]`: every required field gets its own type parameter
+//! on the builder, which is `()` while the field is unset and `(T,)` once it
+//! has been set. `build()` is only implemented when every parameter is set, so
+//! forgetting a required field is a compile error instead of a runtime one.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::{
+    doc_path, each_methods, fallback, option_methods, phantom_type, setter_docs, struct_default,
+    unset_note, Conversion, EachItem, FieldKind, Pattern, SetterSignature, StructOptions, Target,
+};
+
+enum Kind<'a> {
+    /// Tracked by the given type parameter.
+    Required(syn::Ident),
+    /// Stored as `Option<T>`, falling back to the field's default.
+    Defaulted,
+    /// An `Option` field, whose setter takes `T` if `strip_option`.
+    Optional(&'a syn::Type),
+    Each {
+        push_method_name: &'a syn::Ident,
+        item: &'a EachItem,
+        /// The collection if the field is an `Option` of it.
+        optional: Option<&'a syn::Type>,
+    },
+    /// Not in the builder, always its fallback.
+    Skipped,
+}
+
+struct Field<'a> {
+    /// The field as parsed, for the methods shared with other builders.
+    source: &'a crate::Field<'a>,
+    ident: &'a syn::Ident,
+    setter_name: &'a syn::Ident,
+    setter_vis: syn::Visibility,
+    member: &'a syn::Member,
+    ty: &'a syn::Type,
+    kind: Kind<'a>,
+    fallback: Option<TokenStream>,
+    conversion: Conversion,
+    with: Option<&'a syn::ExprClosure>,
+    has_setter: bool,
+    attrs: &'a [syn::Meta],
+    /// Docs of the setter assigning the whole field.
+    docs: TokenStream,
+}
+
+pub fn expand(input: &syn::DeriveInput, target: &Target, options: &StructOptions) -> TokenStream {
+    let struct_name = &input.ident;
+    let builder_name = &target.builder_name;
+    let vis = options.vis();
+    let build_name = options.build_fn_name();
+    let builder_derive = crate::builder_derive(options);
+    let generics = &input.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let struct_ty = quote!(#struct_name #ty_generics);
+    let user_params: Vec<_> = generics.params.iter().collect();
+    let user_args: Vec<_> = generics
+        .params
+        .iter()
+        .map(|param| match param {
+            syn::GenericParam::Lifetime(param) => {
+                let lifetime = &param.lifetime;
+                quote!(#lifetime)
+            }
+            syn::GenericParam::Type(syn::TypeParam { ident, .. })
+            | syn::GenericParam::Const(syn::ConstParam { ident, .. }) => quote!(#ident),
+        })
+        .collect();
+    let fields: Vec<_> = crate::build_order(&target.fields)
+        .map(|field| {
+            let fallback = fallback(field, options);
+            let kind = match field.kind {
+                FieldKind::Optional(ref inner) => Kind::Optional(inner),
+                FieldKind::Each(ref push_method_name, ref item, ref optional) => Kind::Each {
+                    push_method_name,
+                    item,
+                    optional: optional.as_ref(),
+                },
+                FieldKind::Plain if fallback.is_some() => Kind::Defaulted,
+                FieldKind::Plain => Kind::Required(state_param(&field.ident)),
+                FieldKind::Skipped => Kind::Skipped,
+                FieldKind::SubBuilder(_) => unreachable!(),
+            };
+            Field {
+                source: field,
+                ident: &field.ident,
+                setter_name: field.setter_name(),
+                setter_vis: field.options.setter_vis(options),
+                member: &field.member,
+                ty: field.ty,
+                kind,
+                fallback,
+                conversion: field.options.conversion(options),
+                with: field.options.setter.with.as_ref(),
+                has_setter: field.has_setter(),
+                attrs: &field.options.attrs,
+                docs: setter_docs(field, unset_note(field, options)),
+            }
+        })
+        .collect();
+
+    let params: Vec<_> = fields
+        .iter()
+        .filter_map(|field| match field.kind {
+            Kind::Required(ref param) => Some(param),
+            _ => None,
+        })
+        .collect();
+    let mut state_generics = generics.clone();
+    state_generics.params.extend(
+        params
+            .iter()
+            .map(|param| syn::GenericParam::Type(syn::TypeParam::from((*param).clone()))),
+    );
+    let (state_impl_generics, _, _) = state_generics.split_for_impl();
+    // The builder type as seen from inside `impl<#(#params),*>`, with the
+    // state parameter of `target` (if any) replaced by `state`.
+    let builder_with = |target: &syn::Ident, state: TokenStream| {
+        let args = fields.iter().filter_map(|field| match field.kind {
+            Kind::Required(_) if field.ident == target => Some(state.clone()),
+            Kind::Required(ref param) => Some(quote!(#param)),
+            _ => None,
+        });
+        quote!(#builder_name<#(#user_args,)* #(#args),*>)
+    };
+
+    let in_builder = || {
+        fields
+            .iter()
+            .filter(|field| !matches!(field.kind, Kind::Skipped))
+    };
+    let builder_fields = in_builder().map(|field| {
+        let Field {
+            ident,
+            ty,
+            kind,
+            attrs,
+            ..
+        } = field;
+        let ty = match kind {
+            Kind::Required(param) => quote!(#param),
+            _ => quote!(core::option::Option<#ty>),
+        };
+        quote! {
+            #(#[#attrs])*
+            #ident: #ty
+        }
+    });
+    let initial_fields = in_builder().map(|Field { ident, kind, .. }| match kind {
+        Kind::Required(_) => quote!(#ident: ()),
+        _ => quote!(#ident: core::option::Option::None),
+    });
+    let initial_state = params.iter().map(|_| quote!(()));
+    let phantom_move = phantom_type(generics).map(|_| quote!(__phantom: self.__phantom,));
+    let builder_methods = fields.iter().filter(|field| field.has_setter).map(|field| {
+        let Field {
+            ident,
+            setter_name,
+            setter_vis,
+            ty,
+            kind,
+            conversion,
+            with,
+            docs,
+            ..
+        } = field;
+        match kind {
+            Kind::Required(_) => {
+                let SetterSignature {
+                    generics,
+                    params,
+                    conversions,
+                    error,
+                } = SetterSignature::value(ty, *conversion, *with);
+                let return_type = builder_with(ident, quote!((#ty,)));
+                let moved_fields = in_builder().map(|field| {
+                    let other = field.ident;
+                    if other == *ident {
+                        quote!(#other: (value,))
+                    } else {
+                        quote!(#other: self.#other)
+                    }
+                });
+                let builder = quote! {
+                    #builder_name {
+                        #(#moved_fields,)*
+                        #phantom_move
+                    }
+                };
+                let (return_type, builder) = match error {
+                    Some(error) => (
+                        quote!(core::result::Result<#return_type, #error>),
+                        quote!(core::result::Result::Ok(#builder)),
+                    ),
+                    None => (return_type, builder),
+                };
+                quote! {
+                    #docs
+                    #setter_vis fn #setter_name<#(#generics),*>(self, #(#params),*) -> #return_type {
+                        #(#conversions)*
+                        #builder
+                    }
+                }
+            }
+            Kind::Defaulted => {
+                let set_method = Pattern::Owned.setter_signed(
+                    setter_vis,
+                    setter_name,
+                    SetterSignature::value(ty, *conversion, *with),
+                    |this| quote!(#this.#ident = core::option::Option::Some(value);),
+                );
+                quote! {
+                    #docs
+                    #set_method
+                }
+            }
+            Kind::Optional(inner) => {
+                option_methods(Pattern::Owned, field.source, options, inner)
+            }
+            Kind::Skipped => unreachable!(),
+            Kind::Each {
+                push_method_name,
+                item,
+                optional,
+            } => each_methods(
+                Pattern::Owned,
+                field.source,
+                options,
+                push_method_name,
+                item,
+                *optional,
+            ),
+        }
+    });
+    let set_types: Vec<_> = fields
+        .iter()
+        .filter_map(|Field { ty, kind, .. }| match kind {
+            Kind::Required(_) => Some(quote!((#ty,))),
+            _ => None,
+        })
+        .collect();
+    let set_builder = quote!(#builder_name<#(#user_args,)* #(#set_types),*>);
+    let from_fields = in_builder().map(
+        |Field {
+             ident,
+             member,
+             kind,
+             ..
+         }| match kind {
+            Kind::Required(_) => quote!(#ident: (value.#member,)),
+            _ => quote!(#ident: core::option::Option::Some(value.#member)),
+        },
+    );
+    let set_members = fields.iter().map(|field| {
+        let Field { ident, .. } = field;
+        let value = match (&field.kind, &field.fallback) {
+            (Kind::Required(_), _) => quote!(self.#ident.0),
+            (Kind::Skipped, fallback) => quote!(#fallback),
+            (kind, fallback) => {
+                let fallback = fallback.clone().unwrap_or_else(|| match kind {
+                    Kind::Optional { .. } => quote!(core::option::Option::None),
+                    _ => quote!(core::default::Default::default()),
+                });
+                quote! {
+                    match self.#ident {
+                        core::option::Option::Some(v) => v,
+                        core::option::Option::None => #fallback,
+                    }
+                }
+            }
+        };
+        quote!(let #ident = #value;)
+    });
+    let members = fields
+        .iter()
+        .map(|Field { ident, member, .. }| quote!(#member: #ident));
+    let struct_default = struct_default(&struct_ty, options);
+    let defaults = params.iter().map(|param| quote!(#param = ()));
+    let phantom = phantom_type(generics);
+    let phantom_field = phantom.as_ref().map(|phantom| quote!(__phantom: #phantom,));
+    let phantom_default = phantom
+        .as_ref()
+        .map(|_| quote!(__phantom: core::marker::PhantomData,));
+
+    let doc_path = doc_path(struct_name);
+    let constructor_docs = format!("A builder for [`{}`], with no field set.", doc_path);
+    let builder_docs = match options.doc {
+        Some(ref doc) => quote!(#[doc = #doc]),
+        None => {
+            let doc = format!(
+                "Builder for [`{}`], whose `{}()` is only available once every required field \
+                 is set.",
+                doc_path, build_name
+            );
+            quote!(#[doc = #doc])
+        }
+    };
+    let build_docs = format!("Builds the [`{}`].", doc_path);
+
+    let to_builder = crate::to_builder(&vis, &struct_ty, &set_builder);
+    quote! {
+        impl #impl_generics #struct_name #ty_generics #where_clause {
+            #[doc = #constructor_docs]
+            #vis fn builder() -> #builder_name<#(#user_args,)* #(#initial_state),*> {
+                #builder_name {
+                    #(#initial_fields,)*
+                    #phantom_default
+                }
+            }
+
+            #to_builder
+        }
+
+        impl #impl_generics core::convert::From<#struct_ty> for #set_builder #where_clause {
+            fn from(value: #struct_ty) -> Self {
+                #builder_name {
+                    #(#from_fields,)*
+                    #phantom_default
+                }
+            }
+        }
+
+        #builder_docs
+        #[allow(dead_code, non_camel_case_types)]
+        #builder_derive
+        #vis struct #builder_name<#(#user_params,)* #(#defaults),*> #where_clause {
+            #(#builder_fields,)*
+            #phantom_field
+        }
+
+        #[allow(non_camel_case_types)]
+        impl #state_impl_generics #builder_name<#(#user_args,)* #(#params),*> #where_clause {
+            #(#builder_methods)*
+        }
+
+        impl #impl_generics #set_builder #where_clause {
+            #[doc = #build_docs]
+            #vis fn #build_name(self) -> #struct_ty {
+                #struct_default
+                #(#set_members)*
+                #struct_name {
+                    #(#members),*
+                }
+            }
+        }
+    }
+}
+
+/// Name of the type parameter tracking whether `field` has been set.
+fn state_param(field: &syn::Ident) -> syn::Ident {
+    format_ident!("__{}", field.to_string().trim_start_matches("r#"))
+}
@@ -0,0 +1,43 @@
+// With #[builder(typestate)] the builder tracks which required fields have
+// been set in its type, so `build()` can only be called once all of them are
+// present and returns the struct directly instead of a Result.
+//
+// Optional fields and `each` fields are never required and keep working the
+// same way as in the default builder.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    env: Vec<String>,
+    current_dir: Option<String>,
+}
+
+fn main() {
+    let command = Command::builder()
+        .arg("build".to_owned())
+        .executable("cargo".to_owned())
+        .env(vec![])
+        .arg("--release".to_owned())
+        .build();
+
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["build", "--release"]);
+    assert!(command.env.is_empty());
+    assert!(command.current_dir.is_none());
+
+    // Setting a field twice keeps the last value.
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .env(vec!["RUST_LOG=debug".to_owned()])
+        .current_dir("..".to_owned())
+        .executable("rustc".to_owned())
+        .build();
+
+    assert_eq!(command.executable, "rustc");
+    assert_eq!(command.current_dir.as_deref(), Some(".."));
+}
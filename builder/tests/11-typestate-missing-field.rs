@@ -0,0 +1,19 @@
+// In typestate mode, calling build() before every required field has been set
+// must fail to compile rather than return an error at runtime.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Command {
+    executable: String,
+    env: Vec<String>,
+    current_dir: Option<String>,
+}
+
+fn main() {
+    let _command = Command::builder()
+        .env(vec![])
+        .current_dir("..".to_owned())
+        .build();
+}
@@ -0,0 +1,60 @@
+// build() reports a missing field through a generated `CommandBuilderError`
+// enum rather than a boxed string, so callers can match on which field was
+// not set. The enum implements Display and std::error::Error.
+//
+// With #[builder(error = "...")] build() returns the caller's own error type
+// instead, converting from the generated enum through a From impl.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    current_dir: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    Missing(&'static str),
+    Invalid(String),
+}
+
+impl From<RequestBuilderError> for ConfigError {
+    fn from(error: RequestBuilderError) -> Self {
+        match error {
+            RequestBuilderError::UninitializedField(field) => ConfigError::Missing(field),
+            RequestBuilderError::ValidationError(message) => ConfigError::Invalid(message),
+            RequestBuilderError::InvalidField { field, message } => {
+                ConfigError::Invalid(format!("{}: {}", field, message))
+            }
+        }
+    }
+}
+
+#[derive(Builder)]
+#[builder(error = "ConfigError")]
+pub struct Request {
+    url: String,
+    body: Option<String>,
+}
+
+fn main() {
+    let error = Command::builder().current_dir("..".to_owned()).build().err().unwrap();
+    match error {
+        CommandBuilderError::UninitializedField(field) => assert_eq!(field, "executable"),
+        CommandBuilderError::ValidationError(_) | CommandBuilderError::InvalidField { .. } => {
+            unreachable!()
+        }
+    }
+    assert_eq!(error.to_string(), "field `executable` not set");
+
+    let boxed: Box<dyn std::error::Error> = Box::new(error);
+    assert_eq!(boxed.to_string(), "field `executable` not set");
+
+    let error: ConfigError = Request::builder().body("{}".to_owned()).build().err().unwrap();
+    assert_eq!(error, ConfigError::Missing("url"));
+
+    let request = Request::builder().url("/".to_owned()).build().unwrap();
+    assert_eq!(request.url, "/");
+    assert!(request.body.is_none());
+}
@@ -0,0 +1,95 @@
+// A field marked #[builder(default)] falls back to Default::default() when it
+// is not set, and #[builder(default = "...")] falls back to an arbitrary
+// expression. The expression is only evaluated by build() if it is needed.
+//
+// On the struct itself, #[builder(default)] takes every unset field from the
+// struct's own Default impl instead.
+
+use derive_builder::Builder;
+use std::cell::Cell;
+
+thread_local! {
+    static EVALUATED: Cell<u32> = Cell::new(0);
+}
+
+fn default_dir() -> String {
+    EVALUATED.with(|evaluated| evaluated.set(evaluated.get() + 1));
+    "/tmp".to_owned()
+}
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    #[builder(default)]
+    args: Vec<String>,
+    #[builder(default = "default_dir()")]
+    current_dir: String,
+    #[builder(default = "Some(0o022)")]
+    umask: Option<u32>,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(default)]
+pub struct Limits {
+    memory: u64,
+    cpus: u32,
+    name: Option<String>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            memory: 1 << 30,
+            cpus: 4,
+            name: Some("default".to_owned()),
+        }
+    }
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Job {
+    command: String,
+    #[builder(default = "3")]
+    retries: u32,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .build()
+        .unwrap();
+    assert!(command.args.is_empty());
+    assert_eq!(command.current_dir, "/tmp");
+    assert_eq!(command.umask, Some(0o022));
+    assert_eq!(EVALUATED.with(Cell::get), 1);
+
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .current_dir("..".to_owned())
+        .umask(0o077)
+        .build()
+        .unwrap();
+    assert_eq!(command.current_dir, "..");
+    assert_eq!(command.umask, Some(0o077));
+    assert_eq!(EVALUATED.with(Cell::get), 1);
+
+    // Fields without a default are still required.
+    assert!(Command::builder().build().is_err());
+
+    let limits = Limits::builder().cpus(2).build().unwrap();
+    assert_eq!(
+        limits,
+        Limits {
+            memory: 1 << 30,
+            cpus: 2,
+            name: Some("default".to_owned()),
+        },
+    );
+
+    // Defaulted fields are not required by a typestate builder either.
+    let job = Job::builder().command("make".to_owned()).build();
+    assert_eq!(job.retries, 3);
+    let job = Job::builder().retries(0).command("make".to_owned()).build();
+    assert_eq!(job.retries, 0);
+}
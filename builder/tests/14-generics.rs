@@ -0,0 +1,103 @@
+// The builder carries over the struct's lifetimes, type parameters and where
+// clause, both for the default builder and in typestate mode.
+//
+// The builder does not require anything of the type parameters beyond what
+// the struct itself declares. Only build() on a mutable or immutable builder,
+// which clones the values out of it, and the setters of an immutable builder,
+// which clone the builder, need the fields to be Clone.
+
+use derive_builder::Builder;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+#[derive(Builder)]
+pub struct Request<'a, T: Clone, B>
+where
+    B: Debug + Clone,
+{
+    path: &'a str,
+    body: T,
+    backend: Option<B>,
+    #[builder(each = "header")]
+    headers: Vec<(&'a str, T)>,
+}
+
+// A parameter only ever used through PhantomData.
+#[derive(Builder)]
+pub struct Handle<K> {
+    id: u64,
+    kind: PhantomData<K>,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Query<'a, T>
+where
+    T: Default,
+{
+    table: &'a str,
+    filter: Option<T>,
+    #[builder(default)]
+    limit: T,
+}
+
+// No bounds at all: the builder exists for any T, and build() where T: Clone.
+#[derive(Builder)]
+pub struct Reply<T> {
+    body: T,
+}
+
+#[derive(Builder)]
+#[builder(pattern = "immutable")]
+pub struct Event<T> {
+    payload: T,
+}
+
+#[derive(Builder)]
+#[builder(pattern = "owned")]
+pub struct Stream<T> {
+    source: T,
+}
+
+pub struct Postgres;
+
+// Neither Clone nor anything else.
+pub struct Socket;
+
+fn main() {
+    let request = Request::<Vec<u8>, &str>::builder()
+        .path("/upload")
+        .body(vec![1, 2, 3])
+        .header(("Content-Length", vec![3]))
+        .build()
+        .unwrap();
+    assert_eq!(request.path, "/upload");
+    assert_eq!(request.body, [1, 2, 3]);
+    assert!(request.backend.is_none());
+    assert_eq!(request.headers.len(), 1);
+
+    let handle = Handle::<Postgres>::builder()
+        .id(7)
+        .kind(PhantomData)
+        .build()
+        .unwrap();
+    assert_eq!(handle.id, 7);
+
+    let reply = Reply::builder().body("ok").build().unwrap();
+    assert_eq!(reply.body, "ok");
+    let mut builder = Reply::<Socket>::builder();
+    builder.body(Socket);
+    assert!(builder.is_set_body());
+
+    let event = Event::builder().payload(3).build().unwrap();
+    assert_eq!(event.payload, 3);
+
+    let stream = Stream::builder().source(Socket).build().unwrap();
+    let Socket = stream.source;
+
+    let table = String::from("users");
+    let query = Query::builder().filter(5u32).table(&table).build();
+    assert_eq!(query.table, "users");
+    assert_eq!(query.filter, Some(5));
+    assert_eq!(query.limit, 0);
+}
@@ -0,0 +1,61 @@
+// #[builder(pattern = "owned")] makes the setters take and return the builder
+// by value, and build() moves the fields out of it instead of cloning them.
+// This allows fields whose types do not implement Clone.
+//
+// #[builder(pattern = "immutable")] makes the setters take &self and return a
+// modified copy, so one partially configured builder can be branched into
+// several. The default remains pattern = "mutable".
+
+use derive_builder::Builder;
+
+// Deliberately not Clone.
+#[derive(Debug, PartialEq)]
+pub struct Fd(i32);
+
+#[derive(Builder)]
+#[builder(pattern = "owned")]
+pub struct Pipe {
+    read: Fd,
+    write: Fd,
+    #[builder(each = "flag")]
+    flags: Vec<String>,
+    label: Option<String>,
+}
+
+#[derive(Builder)]
+#[builder(pattern = "immutable")]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+}
+
+#[derive(Builder)]
+#[builder(pattern = "mutable")]
+pub struct Limits {
+    cpus: u32,
+}
+
+fn main() {
+    let pipe = Pipe::builder()
+        .read(Fd(3))
+        .write(Fd(4))
+        .flag("O_NONBLOCK".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(pipe.read, Fd(3));
+    assert_eq!(pipe.write, Fd(4));
+    assert_eq!(pipe.flags, ["O_NONBLOCK"]);
+    assert!(pipe.label.is_none());
+
+    let cargo = Command::builder().executable("cargo".to_owned());
+    let build = cargo.arg("build".to_owned());
+    let test = cargo.arg("test".to_owned());
+    assert_eq!(build.build().unwrap().args, ["build"]);
+    assert_eq!(test.build().unwrap().args, ["test"]);
+    assert!(cargo.build().unwrap().args.is_empty());
+
+    let mut builder = Limits::builder();
+    builder.cpus(8);
+    assert_eq!(builder.build().unwrap().cpus, 8);
+}
@@ -0,0 +1,71 @@
+// #[builder(setter(into))] makes a setter generic over any argument that
+// converts into the field's type, so callers can pass a &str for a String
+// field. Given on the struct it applies to every field. For `each` fields the
+// one-at-a-time method converts into the element type.
+//
+// #[builder(setter(try_into))] uses TryInto instead and makes the setter
+// return a Result carrying the conversion error.
+
+use derive_builder::Builder;
+use std::num::TryFromIntError;
+
+#[derive(Builder)]
+pub struct Command {
+    #[builder(setter(into))]
+    executable: String,
+    #[builder(each = "arg", setter(into))]
+    args: Vec<String>,
+    #[builder(setter(into))]
+    current_dir: Option<String>,
+    #[builder(setter(try_into))]
+    nice: i8,
+}
+
+#[derive(Builder)]
+#[builder(setter(into), pattern = "owned")]
+pub struct Header {
+    name: String,
+    value: String,
+    #[builder(setter(try_into))]
+    ttl: u16,
+}
+
+#[derive(Builder)]
+#[builder(typestate, setter(into))]
+pub struct Job {
+    name: String,
+    #[builder(each = "tag")]
+    tags: Vec<String>,
+}
+
+fn main() -> Result<(), TryFromIntError> {
+    let command = Command::builder()
+        .executable("cargo")
+        .arg("build")
+        .arg(String::from("--release"))
+        .current_dir("..")
+        .nice(10i64)?
+        .build()
+        .unwrap();
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, ["build", "--release"]);
+    assert_eq!(command.current_dir.as_deref(), Some(".."));
+    assert_eq!(command.nice, 10);
+
+    assert!(Command::builder().nice(1000).is_err());
+
+    let header = Header::builder()
+        .name("Cache-Control")
+        .value("max-age")
+        .ttl(60u64)?
+        .build()
+        .unwrap();
+    assert_eq!(header.name, "Cache-Control");
+    assert_eq!(header.ttl, 60);
+
+    let job = Job::builder().tag("nightly").name("fuzz").build();
+    assert_eq!(job.name, "fuzz");
+    assert_eq!(job.tags, ["nightly"]);
+
+    Ok(())
+}
@@ -0,0 +1,87 @@
+// #[builder(build_fn(validate = "..."))] names a function that build() calls
+// with the builder before reading any field. Returning Err(message) makes
+// build() fail with CommandBuilderError::ValidationError(message).
+//
+// A field level #[builder(validate = "...")] checks the field's final value,
+// after any default has been applied, and is reported as
+// CommandBuilderError::InvalidField together with the field's name.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(build_fn(validate = "validation::command"))]
+pub struct Command {
+    #[builder(validate = "validation::not_empty")]
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    #[builder(default = "0", validate = "validation::priority")]
+    priority: i32,
+}
+
+mod validation {
+    pub fn command(builder: &super::CommandBuilder) -> Result<(), String> {
+        match builder.args {
+            Some(ref args) if args.len() > 3 => Err("too many arguments".to_owned()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn not_empty(value: &str) -> Result<(), String> {
+        if value.is_empty() {
+            Err("must not be empty".to_owned())
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn priority(value: &i32) -> Result<(), String> {
+        if (-20..20).contains(value) {
+            Ok(())
+        } else {
+            Err(format!("{} is out of range", value))
+        }
+    }
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(command.priority, 0);
+
+    let error = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("a".to_owned())
+        .arg("b".to_owned())
+        .arg("c".to_owned())
+        .arg("d".to_owned())
+        .build()
+        .err()
+        .unwrap();
+    assert!(matches!(error, CommandBuilderError::ValidationError(ref message) if message == "too many arguments"));
+
+    let error = Command::builder()
+        .executable(String::new())
+        .build()
+        .err()
+        .unwrap();
+    assert!(matches!(error, CommandBuilderError::InvalidField { field: "executable", .. }));
+    assert_eq!(
+        error.to_string(),
+        "invalid value for field `executable`: must not be empty",
+    );
+
+    let error = Command::builder()
+        .executable("cargo".to_owned())
+        .priority(99)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(
+        error.to_string(),
+        "invalid value for field `priority`: 99 is out of range",
+    );
+}
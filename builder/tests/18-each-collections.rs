@@ -0,0 +1,72 @@
+// #[builder(each = "...")] works with any collection that implements Default
+// and Extend, not just Vec. For HashMap and BTreeMap the one-at-a-time method
+// takes the key and the value as two arguments.
+//
+// Collections may also be spelled through their full path, such as
+// std::vec::Vec<T>.
+
+use derive_builder::Builder;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: std::vec::Vec<String>,
+    #[builder(each = "env", setter(into))]
+    env: HashMap<String, String>,
+    #[builder(each = "feature")]
+    features: BTreeSet<&'static str>,
+    #[builder(each = "group")]
+    groups: HashSet<u32>,
+    #[builder(each = "limit")]
+    limits: BTreeMap<&'static str, u64>,
+    #[builder(each = "stage")]
+    stages: VecDeque<String>,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Job {
+    name: String,
+    #[builder(each = "label")]
+    labels: BTreeMap<String, String>,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .env("RUST_LOG", "debug")
+        .env("RUST_BACKTRACE", "1")
+        .feature("serde")
+        .feature("serde")
+        .group(100)
+        .limit("memory", 1 << 30)
+        .stage("fetch".to_owned())
+        .stage("compile".to_owned())
+        .build()
+        .unwrap();
+
+    assert_eq!(command.args, ["build"]);
+    assert_eq!(command.env.len(), 2);
+    assert_eq!(command.env["RUST_LOG"], "debug");
+    assert_eq!(command.features.len(), 1);
+    assert!(command.groups.contains(&100));
+    assert_eq!(command.limits["memory"], 1 << 30);
+    assert_eq!(command.stages.front().map(String::as_str), Some("fetch"));
+
+    // Untouched collections come out empty.
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .build()
+        .unwrap();
+    assert!(command.env.is_empty());
+    assert!(command.stages.is_empty());
+
+    let job = Job::builder()
+        .label("team".to_owned(), "infra".to_owned())
+        .name("deploy".to_owned())
+        .build();
+    assert_eq!(job.labels["team"], "infra");
+}
@@ -0,0 +1,68 @@
+// #[derive(Builder)] on an enum generates one builder per variant with named
+// fields. The builder for variant `Connect` is `MessageConnectBuilder`, it is
+// created by `Message::connect_builder()`, and its build() returns a Message.
+// All variant builders share a single MessageBuilderError type. A run of
+// capitals counts as one word in the constructor's name, so `HTTPGet` is built
+// by `Message::http_get_builder()`.
+//
+// Unit and tuple variants do not get a builder.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+pub enum Message {
+    Connect {
+        host: String,
+        port: u16,
+        #[builder(each = "protocol")]
+        protocols: Vec<String>,
+        timeout: Option<u32>,
+    },
+    SendData {
+        #[builder(setter(into))]
+        channel: String,
+        #[builder(default)]
+        payload: Vec<u8>,
+    },
+    HTTPGet {
+        path: String,
+    },
+    Ping,
+    Raw(Vec<u8>),
+}
+
+fn main() {
+    let message = Message::connect_builder()
+        .host("localhost".to_owned())
+        .port(8080)
+        .protocol("h2".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(
+        message,
+        Message::Connect {
+            host: "localhost".to_owned(),
+            port: 8080,
+            protocols: vec!["h2".to_owned()],
+            timeout: None,
+        },
+    );
+
+    let mut builder: MessageSendDataBuilder = Message::send_data_builder();
+    builder.channel("logs");
+    assert_eq!(
+        builder.build().unwrap(),
+        Message::SendData {
+            channel: "logs".to_owned(),
+            payload: Vec::new(),
+        },
+    );
+
+    let error: MessageBuilderError = Message::connect_builder().port(1).build().unwrap_err();
+    assert_eq!(error.to_string(), "field `host` not set");
+
+    let message = Message::http_get_builder().path("/".to_owned()).build().unwrap();
+    assert_eq!(message, Message::HTTPGet { path: "/".to_owned() });
+
+    let _ = (Message::Ping, Message::Raw(Vec::new()));
+}
@@ -0,0 +1,42 @@
+// Tuple structs get a builder too. Their setters are named after the field
+// position, `_0`, `_1` and so on, unless a field is given a name with
+// #[builder(name = "...")]. Optional and `each` fields behave the same as on
+// structs with named fields.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Config(String, Option<u16>);
+
+#[derive(Builder)]
+pub struct Command(
+    #[builder(name = "executable")] String,
+    #[builder(name = "args", each = "arg")] Vec<String>,
+    #[builder(name = "current_dir")] Option<String>,
+);
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Port(u16);
+
+fn main() {
+    let config = Config::builder()._0("server".to_owned()).build().unwrap();
+    assert_eq!(config.0, "server");
+    assert!(config.1.is_none());
+
+    let error = Config::builder()._1(80).build().err().unwrap();
+    assert_eq!(error.to_string(), "field `_0` not set");
+
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .arg("--release".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(command.0, "cargo");
+    assert_eq!(command.1, vec!["build", "--release"]);
+    assert!(command.2.is_none());
+
+    let port = Port::builder()._0(8080).build();
+    assert_eq!(port.0, 8080);
+}
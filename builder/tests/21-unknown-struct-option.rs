@@ -0,0 +1,19 @@
+// Options on the struct itself are checked just like the ones on fields. An
+// unknown key is reported at the key, naming the options that are accepted,
+// and the same goes for keys nested inside `setter(...)` and `build_fn(...)`.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(patern = "owned")]
+pub struct Command {
+    executable: String,
+}
+
+#[derive(Builder)]
+#[builder(setter(int))]
+pub struct Request {
+    url: String,
+}
+
+fn main() {}
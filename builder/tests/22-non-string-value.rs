@@ -0,0 +1,14 @@
+// Every `key = value` option takes its value as a string literal, even when
+// the string holds a path or an expression. Anything else is reported at the
+// value rather than panicking inside the macro.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    #[builder(default = 3)]
+    retries: u32,
+}
+
+fn main() {}
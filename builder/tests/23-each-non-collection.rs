@@ -0,0 +1,13 @@
+// `each` needs a collection type whose item type can be read off its type
+// arguments. Using it on anything else is reported at the field's type.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: String,
+}
+
+fn main() {}
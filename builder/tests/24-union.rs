@@ -0,0 +1,12 @@
+// Only structs and enums can derive a builder; for a union the error points at
+// the `union` keyword.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub union Value {
+    int: u32,
+    float: f32,
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+// Giving the same option twice is an error rather than letting the last one
+// win silently, whether both are in one attribute or spread over several.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(pattern = "owned")]
+#[builder(pattern = "mutable")]
+pub struct Command {
+    executable: String,
+    #[builder(default, default = "0")]
+    retries: u32,
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+// The macro does not stop at the first mistake: every invalid option in the
+// input is reported in the same compile, so they can all be fixed at once.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(pattern = "shared")]
+pub struct Command {
+    #[builder(each = "arg")]
+    executable: String,
+    #[builder(validate = 1)]
+    args: Vec<String>,
+    #[builder(setter(into, into))]
+    env: Vec<String>,
+}
+
+fn main() {}
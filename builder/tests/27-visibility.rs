@@ -0,0 +1,80 @@
+// The builder is private to the module of the derive input by default. A
+// library exporting its builder can give it a visibility with
+// #[builder(vis = "...")], which applies to the builder type, its error type,
+// builder(), build() and the setters. The builder can also be renamed, derive
+// extra traits, and have its build() renamed:
+//
+//     #[builder(name = "...", derive(Debug, Clone), build_fn(name = "..."))]
+//
+// Individual setters can be renamed or given their own visibility with
+// #[builder(setter(name = "...", vis = "..."))] on the field.
+
+mod command {
+    use derive_builder::Builder;
+
+    #[derive(Builder, Debug)]
+    #[builder(
+        name = "CommandOptions",
+        vis = "pub",
+        derive(Debug, Clone),
+        build_fn(name = "finish")
+    )]
+    pub struct Command {
+        pub executable: String,
+        #[builder(each = "arg", setter(name = "arguments"))]
+        pub args: Vec<String>,
+        #[builder(default, setter(vis = "pub(crate)"))]
+        pub current_dir: Option<String>,
+        #[builder(default = "Vec::new()", setter(vis = ""))]
+        pub env: Vec<String>,
+    }
+
+    impl CommandOptions {
+        pub fn inherit_env(&mut self) -> &mut Self {
+            self.env(vec!["PATH".to_owned()])
+        }
+    }
+}
+
+mod pipe {
+    use derive_builder::Builder;
+
+    #[derive(Builder)]
+    #[builder(typestate, vis = "pub", build_fn(name = "finish"))]
+    pub struct Pipe {
+        pub read: i32,
+        #[builder(setter(name = "write_end"))]
+        pub write: i32,
+    }
+}
+
+use command::{Command, CommandBuilderError, CommandOptions};
+
+fn main() {
+    let mut options: CommandOptions = Command::builder();
+    options
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .current_dir("..".to_owned())
+        .inherit_env();
+    let copy = options.clone();
+    assert!(format!("{:?}", copy).starts_with("CommandOptions {"));
+
+    let command = options.finish().unwrap();
+    assert_eq!(command.args, vec!["build"]);
+    assert_eq!(command.env, vec!["PATH"]);
+
+    let command = copy
+        .clone()
+        .arguments(vec!["check".to_owned()])
+        .finish()
+        .unwrap();
+    assert_eq!(command.args, vec!["check"]);
+    assert_eq!(command.current_dir.as_deref(), Some(".."));
+
+    let error: CommandBuilderError = CommandOptions::default().finish().unwrap_err();
+    assert_eq!(error.to_string(), "field `executable` not set");
+
+    let pipe = pipe::Pipe::builder().read(3).write_end(4).finish();
+    assert_eq!((pipe.read, pipe.write), (3, 4));
+}
@@ -0,0 +1,68 @@
+// #[builder(skip)] leaves a field out of the builder altogether: it gets no
+// setter and build() initialises it from Default, or from the expression in
+// #[builder(default = "...")].
+//
+// #[builder(setter(skip))] only leaves out the setter. The builder keeps a
+// slot for the field, so hand-written methods on the builder can still fill
+// it in, and build() falls back to the field's default otherwise.
+//
+// Either way, the default of a field without a setter is worked out after
+// every other field, so its expression can refer to them by name. This is the
+// place for values derived from other fields.
+
+use derive_builder::Builder;
+use std::cell::Cell;
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    #[builder(setter(skip), default = "format!(\"{} {}\", executable, args.join(\" \"))")]
+    line: String,
+    #[builder(skip)]
+    runs: Cell<u32>,
+    #[builder(skip, default = "args.len()")]
+    arg_count: usize,
+}
+
+impl CommandBuilder {
+    fn line_override(&mut self, line: &str) -> &mut Self {
+        self.line = Some(line.to_owned());
+        self
+    }
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Pipe {
+    read: i32,
+    write: i32,
+    #[builder(skip, default = "read.max(write) + 1")]
+    next_fd: i32,
+    #[builder(setter(skip))]
+    label: Option<String>,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .arg("--release".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(command.line, "cargo build --release");
+    assert_eq!(command.runs.get(), 0);
+    assert_eq!(command.arg_count, 2);
+
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .line_override("cargo test")
+        .build()
+        .unwrap();
+    assert_eq!(command.line, "cargo test");
+
+    let pipe = Pipe::builder().read(3).write(4).build();
+    assert_eq!(pipe.next_fd, 5);
+    assert_eq!(pipe.label, None);
+}
@@ -0,0 +1,74 @@
+// The setter of an Option<T> field takes T by default. With
+// #[builder(setter(strip_option = false))], on the field or on the struct, it
+// takes the Option<T> itself instead, so a field can be set to None on
+// purpose. Every optional field also gets a clear_<field>() method which
+// forgets whatever was set, as if the setter had never been called.
+//
+// Because the builder remembers whether an optional field was set at all, an
+// explicit None is kept even when the field has a default, and
+// Option<Option<T>> fields as well as fields spelled std::option::Option<T>
+// behave like any other optional field.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug)]
+pub struct Command {
+    executable: String,
+    current_dir: Option<String>,
+    #[builder(default = "Some(0o022)", setter(strip_option = false))]
+    umask: Option<u32>,
+    timeout: Option<Option<u64>>,
+    nice: std::option::Option<i32>,
+}
+
+#[derive(Builder)]
+#[builder(pattern = "owned", setter(strip_option = false))]
+pub struct Pipe {
+    label: Option<String>,
+    #[builder(setter(strip_option))]
+    buffer: Option<usize>,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Signal {
+    number: i32,
+    #[builder(setter(strip_option = false))]
+    name: Option<String>,
+}
+
+fn main() {
+    let mut builder = Command::builder();
+    builder
+        .executable("cargo".to_owned())
+        .current_dir("..".to_owned())
+        .umask(None)
+        .timeout(None)
+        .nice(10);
+    let command = builder.build().unwrap();
+    assert_eq!(command.current_dir.as_deref(), Some(".."));
+    assert_eq!(command.umask, None);
+    assert_eq!(command.timeout, Some(None));
+    assert_eq!(command.nice, Some(10));
+
+    builder.clear_current_dir().clear_umask().clear_timeout();
+    let command = builder.build().unwrap();
+    assert_eq!(command.current_dir, None);
+    assert_eq!(command.umask, Some(0o022));
+    assert_eq!(command.timeout, None);
+
+    let pipe = Pipe::builder()
+        .label(Some("stdout".to_owned()))
+        .buffer(4096)
+        .build()
+        .unwrap();
+    assert_eq!(pipe.label.as_deref(), Some("stdout"));
+    assert_eq!(pipe.buffer, Some(4096));
+
+    let signal = Signal::builder()
+        .name(Some("SIGTERM".to_owned()))
+        .clear_name()
+        .number(15)
+        .build();
+    assert_eq!((signal.number, signal.name), (15, None));
+}
@@ -0,0 +1,91 @@
+// A value can be turned back into a builder with From, or with to_builder()
+// when it implements Clone, to modify some of its fields and build it again.
+//
+// merge() overlays another builder on this one: every field set in the other
+// builder replaces the one here, and the fields it leaves unset are kept. This
+// is how layered configuration is put together, one partial builder for each
+// of defaults, config file, environment and command line.
+//
+// Enum variants get merge() but no From, since the value could be another
+// variant. Typestate builders are created from a value with every required
+// field already set.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Clone, Debug, PartialEq)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    current_dir: Option<String>,
+    #[builder(default = "3")]
+    retries: u32,
+}
+
+// Deliberately not Clone, so only From is available.
+#[derive(Builder)]
+#[builder(pattern = "owned")]
+pub struct Pipe {
+    read: std::fs::File,
+    label: Option<String>,
+}
+
+#[derive(Builder, Clone)]
+#[builder(typestate)]
+pub struct Signal {
+    number: i32,
+    name: Option<String>,
+}
+
+#[derive(Builder)]
+pub enum Message {
+    Connect { host: String, port: u16 },
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .build()
+        .unwrap();
+    let rebuilt = command
+        .to_builder()
+        .current_dir("..".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(rebuilt.args, vec!["build"]);
+    assert_eq!(rebuilt.current_dir.as_deref(), Some(".."));
+    assert_eq!(CommandBuilder::from(command.clone()).build().unwrap(), command);
+
+    let mut defaults = Command::builder();
+    defaults.executable("cargo".to_owned()).retries(1);
+    let mut file = Command::builder();
+    file.retries(5).current_dir("/tmp".to_owned());
+    let mut flags = Command::builder();
+    flags.arg("test".to_owned());
+    let command = defaults.merge(file).merge(flags).build().unwrap();
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["test"]);
+    assert_eq!(command.current_dir.as_deref(), Some("/tmp"));
+    assert_eq!(command.retries, 5);
+
+    let read = std::fs::File::open("/dev/null").unwrap();
+    let pipe = PipeBuilder::default().read(read).build().unwrap();
+    let pipe = PipeBuilder::from(pipe)
+        .merge(PipeBuilder::default().label("null".to_owned()))
+        .build()
+        .unwrap();
+    assert_eq!(pipe.label.as_deref(), Some("null"));
+
+    let signal = Signal::builder().number(15).build();
+    let signal = signal.to_builder().name("SIGTERM".to_owned()).build();
+    assert_eq!((signal.number, signal.name.as_deref()), (15, Some("SIGTERM")));
+
+    let mut connect = Message::connect_builder();
+    connect.host("localhost".to_owned());
+    let mut port = Message::connect_builder();
+    port.port(8080);
+    match connect.merge(port).build().unwrap() {
+        Message::Connect { host, port } => assert_eq!((host.as_str(), port), ("localhost", 8080)),
+    }
+}
@@ -0,0 +1,56 @@
+// With the `serde` feature of this crate, #[builder(serde)] makes the builder
+// derive serde::Deserialize, with all of its fields optional. A partial config
+// read from JSON, TOML or any other format becomes a builder, and build() then
+// checks that the required fields were all given somewhere.
+//
+// Attributes for the builder's own fields, serde's included, are given with
+// #[builder(field(attrs(...)))]. Serialize can be added with
+// #[builder(serde, derive(serde::Serialize))]; fields that were never set are left out
+// of the output.
+//
+// This test only runs with `cargo test --features serde`.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug)]
+#[builder(serde, derive(serde::Serialize))]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    #[builder(field(attrs(serde(rename = "cwd"))))]
+    current_dir: Option<String>,
+    #[builder(default = "3")]
+    retries: u32,
+}
+
+#[derive(Builder)]
+#[builder(serde)]
+pub struct Wrapper<T: Clone> {
+    inner: T,
+}
+
+fn main() {
+    let mut file: CommandBuilder = serde_json::from_str(r#"{"args": ["build"], "cwd": ".."}"#).unwrap();
+    let mut flags = Command::builder();
+    flags.executable("cargo".to_owned());
+    let command = file.merge(flags).build().unwrap();
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["build"]);
+    assert_eq!(command.current_dir.as_deref(), Some(".."));
+    assert_eq!(command.retries, 3);
+
+    let partial: CommandBuilder = serde_json::from_str(r#"{"retries": 1}"#).unwrap();
+    assert_eq!(
+        partial.build().unwrap_err().to_string(),
+        "field `executable` not set",
+    );
+
+    let mut builder = Command::builder();
+    builder.executable("cargo".to_owned()).current_dir(".".to_owned());
+    let json = serde_json::to_string(&builder).unwrap();
+    assert_eq!(json, r#"{"executable":"cargo","cwd":"."}"#);
+
+    let wrapper: WrapperBuilder<u8> = serde_json::from_str(r#"{"inner": 7}"#).unwrap();
+    assert_eq!(wrapper.build().unwrap().inner, 7);
+}
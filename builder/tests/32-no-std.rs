@@ -0,0 +1,72 @@
+// #[builder(no_std)] makes the generated code usable from a #![no_std] crate
+// with `alloc`: everything is referred to through core:: and alloc:: paths,
+// and the error type holds an alloc::string::String for validation messages
+// and implements Display, but not std::error::Error.
+//
+// std is linked under another name below only so that this test can run as an
+// ordinary binary. The name `std` itself is never in scope, so any path through
+// it in the generated code would fail to compile.
+
+#![no_std]
+
+extern crate alloc;
+extern crate std as host;
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use derive_builder::Builder;
+
+#[derive(Builder, Debug)]
+#[builder(no_std, build_fn(validate = "Self::check"))]
+pub struct Packet<'a, T: Clone> {
+    header: u8,
+    #[builder(each = "byte")]
+    payload: Vec<u8>,
+    #[builder(default)]
+    checksum: Option<u16>,
+    #[builder(setter(into), default = "\"none\"")]
+    label: &'a str,
+    extra: Option<T>,
+}
+
+impl<'a, T: Clone> PacketBuilder<'a, T> {
+    fn check(&self) -> Result<(), String> {
+        match self.header {
+            Some(0) => Err("header must not be zero".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Builder)]
+#[builder(no_std, typestate)]
+pub struct Frame {
+    id: u32,
+    data: Option<u64>,
+}
+
+#[derive(Builder, Debug)]
+#[builder(no_std)]
+pub enum Command {
+    Reset { delay: u32 },
+}
+
+fn main() {
+    let packet = Packet::<()>::builder()
+        .header(1)
+        .byte(0xde)
+        .byte(0xad)
+        .build()
+        .unwrap();
+    host::assert_eq!(packet.payload, [0xde, 0xad]);
+    host::assert_eq!(packet.label, "none");
+
+    let error = Packet::<()>::builder().header(0).build().unwrap_err();
+    host::assert_eq!(error.to_string(), "header must not be zero");
+
+    let frame = Frame::builder().id(7).build();
+    host::assert_eq!((frame.id, frame.data), (7, None));
+
+    let reset = Command::reset_builder().build().unwrap_err();
+    host::assert_eq!(reset.to_string(), "field `delay` not set");
+}
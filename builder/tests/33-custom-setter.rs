@@ -0,0 +1,137 @@
+// #[builder(setter(custom))] generates no setter for a field, but unlike
+// setter(skip) the field stays required: the builder keeps its slot, a
+// hand-written impl on the builder fills it in, and build() reports it as
+// uninitialized if that never happens. This is the way to write setters that
+// are async or need arguments the macro can't express.
+//
+// #[builder(setter(with = "|..| ..."))] keeps the generated setter but runs
+// the given closure on its arguments first. The setter takes the closure's
+// parameters, so they need explicit types. A closure returning Result<T, E>
+// makes the setter fallible, returning Result<Builder, E> in turn.
+//
+// A function works too, given with its signature as a cast to a function
+// pointer: #[builder(setter(with = "path::to_fn as fn(&str) -> u16"))]. The
+// macro can't look up the function itself, so the signature is what tells it
+// the setter's parameters and whether it is fallible.
+
+use derive_builder::Builder;
+use std::num::ParseIntError;
+use std::time::Duration;
+
+#[derive(Builder, Debug)]
+pub struct Command {
+    #[builder(setter(custom))]
+    executable: String,
+    #[builder(setter(with = "|host: &str, port: u16| format!(\"{}:{}\", host, port)"))]
+    address: String,
+    #[builder(setter(with = "|secs: &str| -> Result<u64, ParseIntError> { secs.parse() }"))]
+    timeout_secs: u64,
+    #[builder(setter(with = "|millis: u64| Duration::from_millis(millis)"))]
+    delay: Option<Duration>,
+    #[builder(setter(with = "parse_port as fn(&str) -> Result<u16, ParseIntError>"))]
+    port: u16,
+    #[builder(setter(with = "join as fn(user: &str, host: &str) -> String"))]
+    login: String,
+}
+
+fn parse_port(port: &str) -> Result<u16, ParseIntError> {
+    port.parse()
+}
+
+fn join(user: &str, host: &str) -> String {
+    format!("{}@{}", user, host)
+}
+
+impl CommandBuilder {
+    async fn resolve(&mut self, name: &str) -> &mut Self {
+        self.executable = Some(format!("/usr/bin/{}", name));
+        self
+    }
+
+    fn executable(&mut self, path: &str) -> Result<&mut Self, String> {
+        if !path.starts_with('/') {
+            return Err(format!("{} is not an absolute path", path));
+        }
+        self.executable = Some(path.to_owned());
+        Ok(self)
+    }
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Server {
+    #[builder(setter(with = "|port: &str| -> Result<u16, ParseIntError> { port.parse() }"))]
+    port: u16,
+    #[builder(default, setter(with = "|name: &str| name.to_uppercase()"))]
+    name: String,
+    #[builder(default, setter(with = "str::len as fn(&str) -> usize"))]
+    name_len: usize,
+}
+
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    fn raw_waker() -> RawWaker {
+        fn clone(_: *const ()) -> RawWaker {
+            raw_waker()
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+
+    let waker = unsafe { Waker::from_raw(raw_waker()) };
+    let mut future = Box::pin(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut Context::from_waker(&waker)) {
+            return output;
+        }
+    }
+}
+
+fn main() {
+    let mut builder = Command::builder();
+    block_on(builder.resolve("cargo"));
+    let command = builder
+        .address("localhost", 8080)
+        .timeout_secs("30")
+        .unwrap()
+        .delay(250)
+        .port("8080")
+        .unwrap()
+        .login("root", "localhost")
+        .build()
+        .unwrap();
+    assert_eq!(command.executable, "/usr/bin/cargo");
+    assert_eq!(command.address, "localhost:8080");
+    assert_eq!(command.timeout_secs, 30);
+    assert_eq!(command.delay, Some(Duration::from_millis(250)));
+    assert_eq!(command.port, 8080);
+    assert_eq!(command.login, "root@localhost");
+    assert!(Command::builder().port("http").is_err());
+
+    let mut builder = Command::builder();
+    assert!(builder.executable("cargo").is_err());
+    assert!(builder.timeout_secs("soon").is_err());
+    let err = builder
+        .address("localhost", 8080)
+        .timeout_secs("30")
+        .unwrap()
+        .port("8080")
+        .unwrap()
+        .login("root", "localhost")
+        .build()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "field `executable` not set");
+
+    let server = Server::builder()
+        .port("8080")
+        .unwrap()
+        .name("web")
+        .name_len("web")
+        .build();
+    assert_eq!(server.name_len, 3);
+    assert_eq!(server.port, 8080);
+    assert_eq!(server.name, "WEB");
+    assert!(Server::builder().port("http").is_err());
+}
@@ -0,0 +1,148 @@
+// A field whose type derives Builder too can be marked
+// #[builder(sub_builder)]. The builder then holds the field type's own
+// builder, reached through a `{field}_mut()` method, so nested configuration
+// is filled in place instead of being built separately and passed in. The
+// plain setter still takes a finished value.
+//
+// build() builds every sub-builder, including ones never touched unless the
+// field has a default. Errors from a sub-builder come back as the SubField
+// variant, naming the field at fault by its path from the outer builder.
+//
+// The field type's builder is assumed to be `{Type}Builder`; otherwise give
+// it as #[builder(sub_builder = "...")].
+//
+// Inner and outer builders may use different patterns and error types, with
+// two exceptions where the inner builder has to be Clone: an owned one inside
+// a mutable or immutable outer builder, whose build() only borrows it, and any
+// inside an immutable outer builder, which is itself Clone.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Clone, Debug, PartialEq)]
+pub struct Tls {
+    cert: String,
+    #[builder(default)]
+    verify: bool,
+}
+
+#[derive(Builder, Clone, Debug, PartialEq)]
+pub struct Server {
+    host: String,
+    #[builder(validate = "check_port")]
+    port: u16,
+    #[builder(sub_builder)]
+    tls: Tls,
+}
+
+fn check_port(port: &u16) -> Result<(), String> {
+    if *port == 0 {
+        return Err("must not be zero".to_owned());
+    }
+    Ok(())
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(name = "LimitsConfig")]
+pub struct Limits {
+    #[builder(default = "64")]
+    connections: u32,
+}
+
+#[derive(Builder, Debug)]
+#[builder(pattern = "owned")]
+pub struct Config {
+    #[builder(sub_builder)]
+    server: Server,
+    #[builder(sub_builder = "LimitsConfig", default = "Limits { connections: 8 }")]
+    limits: Limits,
+}
+
+#[derive(Builder, Debug)]
+#[builder(pattern = "owned", derive(Clone))]
+pub struct Retry {
+    attempts: u32,
+}
+
+#[derive(Debug)]
+pub struct PolicyError(String);
+
+impl From<PolicyBuilderError> for PolicyError {
+    fn from(error: PolicyBuilderError) -> Self {
+        PolicyError(error.to_string())
+    }
+}
+
+#[derive(Builder, Debug)]
+#[builder(error = "PolicyError")]
+pub struct Policy {
+    name: String,
+}
+
+#[derive(Builder, Debug)]
+pub struct Client {
+    #[builder(sub_builder)]
+    retry: Retry,
+    #[builder(sub_builder)]
+    policy: Policy,
+}
+
+fn main() {
+    let mut builder = Config::builder();
+    builder
+        .server_mut()
+        .host("localhost".to_owned())
+        .port(443)
+        .tls_mut()
+        .cert("server.pem".to_owned());
+    let config = builder.build().unwrap();
+    assert_eq!(config.server.host, "localhost");
+    assert_eq!(config.server.tls.cert, "server.pem");
+    assert!(!config.server.tls.verify);
+    assert_eq!(config.limits.connections, 8);
+
+    let mut builder = Config::builder();
+    builder.limits_mut().connections(16);
+    let config = builder.server(config.server.clone()).build().unwrap();
+    assert_eq!(config.server.port, 443);
+    assert_eq!(config.limits.connections, 16);
+
+    let mut builder = Config::builder();
+    builder.server_mut().host("localhost".to_owned()).port(443);
+    let err = builder.build().unwrap_err();
+    match err {
+        ConfigBuilderError::SubField { ref field, ref message } => {
+            assert_eq!(field, "server.tls.cert");
+            assert_eq!(message, "not set");
+        }
+        _ => unreachable!(),
+    }
+    assert_eq!(err.to_string(), "server.tls.cert: not set");
+
+    let mut builder = Config::builder();
+    builder
+        .server_mut()
+        .host("localhost".to_owned())
+        .port(0)
+        .tls_mut()
+        .cert("server.pem".to_owned());
+    let err = builder.build().unwrap_err();
+    assert_eq!(err.to_string(), "server.port: must not be zero");
+
+    let err = Config::builder().build().unwrap_err();
+    assert_eq!(err.to_string(), "server.host: not set");
+
+    let mut builder = Client::builder();
+    builder.retry(Retry::builder().attempts(3).build().unwrap());
+    builder.policy_mut().name("strict".to_owned());
+    let client = builder.build().unwrap();
+    assert_eq!(client.retry.attempts, 3);
+    assert_eq!(client.policy.name, "strict");
+
+    let err = Client::builder().build().unwrap_err();
+    assert_eq!(err.to_string(), "retry.attempts: not set");
+    let err = Client::builder()
+        .retry(Retry::builder().attempts(3).build().unwrap())
+        .build()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "policy.name: not set");
+}
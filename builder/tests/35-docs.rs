@@ -0,0 +1,77 @@
+// The builder, its methods and its error type all carry docs, so a crate
+// exposing a builder can #![deny(missing_docs)]. A field's own docs are
+// repeated on its setters, followed by a line saying whether the field is
+// required, optional or defaulted. #[builder(doc = "...")] replaces the docs
+// of a field's setters, or of the builder itself when given on the struct.
+
+#![deny(missing_docs)]
+//! Checks that generated builders need no docs of their own.
+
+use derive_builder::Builder;
+
+/// A command to run.
+#[derive(Builder)]
+#[builder(vis = "pub")]
+pub struct Command {
+    /// Path to the program.
+    pub executable: String,
+    /// Arguments passed to the program.
+    #[builder(each = "arg")]
+    pub args: Vec<String>,
+    /// Working directory, inherited if unset.
+    pub current_dir: Option<String>,
+    /// Environment variables, as `(name, value)` pairs.
+    #[builder(default, doc = "Sets every environment variable at once.")]
+    pub env: Vec<(String, String)>,
+    /// Where to send the output.
+    #[builder(sub_builder)]
+    pub output: Output,
+}
+
+/// Output settings.
+#[derive(Builder, Clone)]
+#[builder(vis = "pub", doc = "Configures where output goes.")]
+pub struct Output {
+    /// Whether to capture stdout.
+    #[builder(default)]
+    pub capture: bool,
+}
+
+/// A connection.
+#[derive(Builder)]
+#[builder(vis = "pub", typestate)]
+pub struct Connection {
+    /// Host to connect to.
+    pub host: String,
+    /// Port, 80 unless set.
+    #[builder(default = "80")]
+    pub port: u16,
+    /// Proxy to go through.
+    pub proxy: Option<String>,
+}
+
+/// Messages.
+#[derive(Builder)]
+#[builder(vis = "pub")]
+pub enum Message {
+    /// Opens a connection.
+    Connect {
+        /// Where to connect.
+        host: String,
+    },
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .build()
+        .unwrap();
+    assert!(command.current_dir.is_none());
+    assert!(!command.output.capture);
+    let connection = Connection::builder().host("localhost".to_owned()).build();
+    assert_eq!(connection.port, 80);
+    match Message::connect_builder().host("localhost".to_owned()).build().unwrap() {
+        Message::Connect { host } => assert_eq!(host, "localhost"),
+    }
+}
@@ -0,0 +1,110 @@
+// #[builder(default_with = "|builder| ...")] works out a field's default from
+// the rest of the builder when the field was never set. The closure gets a
+// reference to the builder, whose slots are Options holding what the caller
+// set, so it can derive one field from another. The closure's parameter
+// needs no type, as in `Window::depth` below.
+//
+// Closures may read fields that have a default_with of their own: build()
+// fills those in first, in dependency order, so `log_file` below sees the
+// `current_dir` that was worked out from `executable`. Closures that depend
+// on each other in a cycle are a compile error.
+//
+// Dependencies are found from `builder.field` reads. A closure that uses the
+// builder in any other way, like `Job::summary` below passing it to a helper,
+// is taken to read every field, so it runs after all other closures.
+//
+// Sub-builders need not be Clone, so a mutable or immutable builder can't
+// hand them to the closures: there a closure reading a sub-builder field is a
+// compile error, and one using the builder opaquely sees them unset. A
+// sub-builder field can still have a default_with of its own.
+
+use derive_builder::Builder;
+use std::path::{Path, PathBuf};
+
+#[derive(Builder, Debug)]
+pub struct Command {
+    executable: PathBuf,
+    #[builder(default_with = "|builder: &CommandBuilder| builder.current_dir.clone().unwrap().join(\"run.log\")")]
+    log_file: PathBuf,
+    #[builder(default_with = "|builder: &CommandBuilder| builder.executable.as_ref().and_then(|executable| executable.parent()).map_or_else(|| PathBuf::from(\".\"), Path::to_path_buf)")]
+    current_dir: PathBuf,
+    #[builder(default_with = "|b: &CommandBuilder| b.log_file.as_ref().map(|log| log.with_extension(\"err\"))")]
+    error_log: Option<PathBuf>,
+}
+
+#[derive(Builder, Debug)]
+#[builder(pattern = "owned")]
+pub struct Window {
+    width: u32,
+    #[builder(default_with = "|b: &WindowBuilder| b.width.map_or(0, |width| width * 3 / 4)")]
+    height: u32,
+    #[builder(default_with = "|b| b.height.unwrap_or(0) + 1")]
+    depth: u32,
+}
+
+#[derive(Builder, Debug)]
+pub struct Job {
+    #[builder(default_with = "|b: &JobBuilder| describe(b)")]
+    summary: String,
+    name: String,
+    #[builder(default_with = "|b: &JobBuilder| b.name.clone().map(|name| format!(\"{}.log\", name))")]
+    log: Option<String>,
+}
+
+#[derive(Builder, Debug)]
+pub struct Limits {
+    connections: u32,
+}
+
+#[derive(Builder, Debug)]
+pub struct Server {
+    port: u16,
+    #[builder(default_with = "|b| b.port.map_or(0, |port| port + 1)")]
+    admin_port: u16,
+    #[builder(sub_builder, default_with = "|b| Limits { connections: u32::from(b.port.unwrap_or(0)) }")]
+    limits: Limits,
+}
+
+fn describe(builder: &JobBuilder) -> String {
+    let name = builder.name.as_deref().unwrap_or("?");
+    let log = builder.log.clone().flatten().unwrap_or_default();
+    format!("{} > {}", name, log)
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable(PathBuf::from("/usr/bin/cargo"))
+        .build()
+        .unwrap();
+    assert_eq!(command.current_dir, Path::new("/usr/bin"));
+    assert_eq!(command.log_file, Path::new("/usr/bin/run.log"));
+    assert_eq!(command.error_log.as_deref(), Some(Path::new("/usr/bin/run.err")));
+
+    let mut builder = Command::builder();
+    builder
+        .executable(PathBuf::from("/usr/bin/cargo"))
+        .current_dir(PathBuf::from("/tmp"));
+    let command = builder.build().unwrap();
+    assert_eq!(command.log_file, Path::new("/tmp/run.log"));
+    assert_eq!(command.error_log.as_deref(), Some(Path::new("/tmp/run.err")));
+
+    // build() on a mutable builder leaves the builder itself untouched.
+    assert!(builder.log_file.is_none());
+
+    let window = Window::builder().width(800).build().unwrap();
+    assert_eq!(window.height, 600);
+    assert_eq!(window.depth, 601);
+    let window = Window::builder().width(800).height(100).build().unwrap();
+    assert_eq!(window.height, 100);
+
+    let server = Server::builder().port(80).build().unwrap();
+    assert_eq!(server.admin_port, 81);
+    assert_eq!(server.limits.connections, 80);
+    let mut builder = Server::builder();
+    builder.port(80).limits_mut().connections(4);
+    let server = builder.build().unwrap();
+    assert_eq!(server.limits.connections, 4);
+
+    let job = Job::builder().name("test".to_owned()).build().unwrap();
+    assert_eq!(job.summary, "test > test.log");
+}
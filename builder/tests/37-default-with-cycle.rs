@@ -0,0 +1,30 @@
+// Two default_with closures reading each other's field can't be ordered, so
+// the derive rejects them instead of generating a build() that could never
+// fill either in.
+//
+// A closure that does more with the builder than read its fields is taken to
+// read all of them, so two such closures are a cycle as well.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Rect {
+    #[builder(default_with = "|b: &RectBuilder| b.height.map_or(1, |height| height * 2)")]
+    width: u32,
+    #[builder(default_with = "|b: &RectBuilder| b.width.map_or(1, |width| width / 2)")]
+    height: u32,
+}
+
+#[derive(Builder)]
+pub struct Job {
+    #[builder(default_with = "|b: &JobBuilder| describe(b)")]
+    summary: String,
+    #[builder(default_with = "|b: &JobBuilder| describe(b).len()")]
+    width: usize,
+}
+
+fn describe(_builder: &JobBuilder) -> String {
+    String::new()
+}
+
+fn main() {}
@@ -0,0 +1,79 @@
+// Struct-level field groups constrain which fields may be set together,
+// checked at the start of build():
+//
+//   one_of(a, b, ...)          exactly one of the fields is set
+//   at_most_one_of(a, b, ...)  no more than one of them is set
+//   requires(a, b, ...)        either all of them are set, or none
+//
+// A field counts as set once one of its setters was called, whatever its
+// default. Violations come back as the MissingOneOf, ConflictingFields and
+// RequiresField variants of the error enum, which only exist for structs
+// that declare groups.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug)]
+#[builder(
+    one_of(executable, script),
+    at_most_one_of(quiet, verbose),
+    requires(tls_cert, tls_key)
+)]
+pub struct Command {
+    executable: Option<String>,
+    script: Option<String>,
+    #[builder(default)]
+    quiet: bool,
+    #[builder(default)]
+    verbose: bool,
+    tls_cert: Option<String>,
+    tls_key: Option<String>,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .verbose(true)
+        .tls_cert("cert.pem".to_owned())
+        .tls_key("key.pem".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(command.executable.as_deref(), Some("cargo"));
+    assert!(command.script.is_none());
+    assert!(command.verbose && !command.quiet);
+
+    let err = Command::builder().build().unwrap_err();
+    match err {
+        CommandBuilderError::MissingOneOf(fields) => assert_eq!(fields, ["executable", "script"]),
+        _ => unreachable!(),
+    }
+    assert_eq!(err.to_string(), "one of `executable`, `script` must be set");
+
+    let err = Command::builder()
+        .executable("cargo".to_owned())
+        .script("build.sh".to_owned())
+        .build()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "only one of `executable`, `script` can be set");
+
+    let err = Command::builder()
+        .script("build.sh".to_owned())
+        .quiet(true)
+        .verbose(false)
+        .build()
+        .unwrap_err();
+    assert!(matches!(err, CommandBuilderError::ConflictingFields(["quiet", "verbose"])));
+
+    let err = Command::builder()
+        .script("build.sh".to_owned())
+        .tls_key("key.pem".to_owned())
+        .build()
+        .unwrap_err();
+    match err {
+        CommandBuilderError::RequiresField { field, required } => {
+            assert_eq!(field, "tls_key");
+            assert_eq!(required, "tls_cert");
+        }
+        _ => unreachable!(),
+    }
+    assert_eq!(err.to_string(), "field `tls_key` requires `tls_cert` to be set too");
+}
@@ -0,0 +1,61 @@
+// #[builder(const)] makes builder(), every setter and build() `const fn`s, so
+// a builder can initialise `const` and `static` items. The builder uses the
+// owned pattern, and build() returns the generated error enum directly, which
+// then only has the UninitializedField variant: it owns nothing, so a
+// constant expression can match on the result and discard the error.
+//
+// Everything build() and the setters do must be possible in a const fn, so
+// conversions, validators, `each`, `default_with` and fields owning heap
+// memory are compile errors, as are defaults going through
+// Default::default(). Defaults written as expressions are fine as long as the
+// expression is itself const.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(const)]
+pub struct Device {
+    name: &'static str,
+    irq: u8,
+    #[builder(default = "0x1000")]
+    base: usize,
+    dma: Option<u8>,
+    #[builder(setter(skip), default = "[0; 4]")]
+    registers: [u32; 4],
+}
+
+const fn device(builder: DeviceBuilder) -> Device {
+    match builder.build() {
+        Ok(device) => device,
+        Err(_) => panic!("incomplete device"),
+    }
+}
+
+const UART: Device = device(Device::builder().name("uart0").irq(4).dma(2));
+
+static DEVICES: [Device; 2] = [
+    device(Device::builder().name("timer").irq(0).base(0x2000)),
+    device(
+        Device::builder()
+            .name("spi")
+            .irq(7)
+            .dma(1)
+            .clear_dma()
+            .merge(Device::builder().base(0x3000)),
+    ),
+];
+
+fn main() {
+    assert_eq!(UART.name, "uart0");
+    assert_eq!(UART.base, 0x1000);
+    assert_eq!(UART.dma, Some(2));
+    assert_eq!(DEVICES[0].base, 0x2000);
+    assert_eq!(DEVICES[1].dma, None);
+    assert_eq!(DEVICES[1].base, 0x3000);
+    assert_eq!(DEVICES[1].registers, [0; 4]);
+
+    match Device::builder().name("gpio").build() {
+        Err(DeviceBuilderError::UninitializedField(field)) => assert_eq!(field, "irq"),
+        Ok(_) => unreachable!(),
+    }
+}
@@ -0,0 +1,18 @@
+// A const builder rejects what it could not do in a const fn with an error
+// on the offending attribute or type, rather than leaving rustc to complain
+// about the generated code.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(const)]
+pub struct Device {
+    name: String,
+    #[builder(each = "register")]
+    registers: Vec<u32>,
+    aliases: Option<Vec<&'static str>>,
+    #[builder(default)]
+    base: usize,
+}
+
+fn main() {}
@@ -0,0 +1,83 @@
+// #[builder(each = "...")] also works on an Option of a collection. The field
+// stays None until something is added, and the first item turns it into Some.
+// Like any optional field it keeps its whole-value setter and clear_<field>().
+//
+// Every `each` field additionally gets an extend_<field>() method which adds
+// all items of an iterator at once, without building up a collection first.
+// For maps the iterator yields (key, value) pairs.
+
+use derive_builder::Builder;
+use std::collections::BTreeMap;
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    #[builder(each = "env")]
+    env: BTreeMap<&'static str, &'static str>,
+    #[builder(each = "feature")]
+    features: Option<Vec<&'static str>>,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Job {
+    name: String,
+    #[builder(each = "label")]
+    labels: Option<Vec<String>>,
+    #[builder(each = "step")]
+    steps: Vec<u32>,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .extend_args(vec!["--release".to_owned(), "--locked".to_owned()])
+        .extend_env([("RUST_LOG", "debug"), ("CARGO_TERM_COLOR", "always")])
+        .build()
+        .unwrap();
+    assert_eq!(command.args, ["build", "--release", "--locked"]);
+    assert_eq!(command.env.len(), 2);
+    assert_eq!(command.features, None);
+
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .feature("serde")
+        .extend_features(["std", "alloc"])
+        .build()
+        .unwrap();
+    assert_eq!(command.features, Some(vec!["serde", "std", "alloc"]));
+
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .features(vec!["serde"])
+        .feature("std")
+        .build()
+        .unwrap();
+    assert_eq!(command.features, Some(vec!["serde", "std"]));
+
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .feature("serde")
+        .clear_features()
+        .build()
+        .unwrap();
+    assert_eq!(command.features, None);
+
+    let job = Job::builder()
+        .name("test".to_owned())
+        .extend_steps(1..=3)
+        .step(4)
+        .build();
+    assert_eq!(job.steps, [1, 2, 3, 4]);
+    assert_eq!(job.labels, None);
+
+    let job = Job::builder()
+        .label("ci".to_owned())
+        .extend_labels(vec!["nightly".to_owned()])
+        .name("test".to_owned())
+        .build();
+    assert_eq!(job.labels, Some(vec!["ci".to_owned(), "nightly".to_owned()]));
+}
@@ -0,0 +1,108 @@
+// Builders can be asked what they hold before build() is called, for example
+// to show which fields of a form still need filling in:
+//
+// - is_set_<field>() tells whether a field has been set, which for an
+//   Option field includes an explicit None. Like clear_<field>(), it follows
+//   the name of the field's setter;
+// - missing_fields() names the fields build() would report as unset, by the
+//   field's name without any r# prefix;
+// - FIELDS lists every field of the builder with its type and what build()
+//   does if it is never set, described by the {Struct}FieldInfo and
+//   {Struct}FieldStatus types generated next to the builder.
+//
+// A sub-builder field is missing while its own builder is. Typestate builders
+// have none of this, as their type already tells which fields are set.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug)]
+pub struct Tls {
+    cert: String,
+    #[builder(default)]
+    verify: bool,
+}
+
+#[derive(Builder, Debug)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    #[builder(setter(strip_option = false))]
+    current_dir: Option<String>,
+    #[builder(default = "30")]
+    timeout: u64,
+    env: std::collections::HashMap<&'static str, &'static str>,
+    #[builder(sub_builder)]
+    tls: Tls,
+}
+
+#[derive(Builder)]
+pub enum Message {
+    Connect { host: String, port: u16 },
+}
+
+#[derive(Builder)]
+pub struct Request {
+    #[builder(setter(name = "url"))]
+    uri: String,
+    r#type: String,
+}
+
+fn main() {
+    let mut builder = Command::builder();
+    assert!(!builder.is_set_executable());
+    assert_eq!(builder.missing_fields(), ["executable", "env", "tls"]);
+
+    builder
+        .executable("cargo".to_owned())
+        .current_dir(None)
+        .tls_mut()
+        .verify(true);
+    assert!(builder.is_set_executable());
+    assert!(builder.is_set_current_dir());
+    assert!(!builder.is_set_args());
+    assert!(builder.is_set_tls());
+    assert_eq!(builder.missing_fields(), ["env", "tls"]);
+
+    builder
+        .env(Default::default())
+        .tls_mut()
+        .cert("client.pem".to_owned());
+    assert!(builder.missing_fields().is_empty());
+    assert!(builder.build().is_ok());
+
+    let fields: Vec<_> = CommandBuilder::FIELDS
+        .iter()
+        .map(|field| (field.name, field.ty, field.status))
+        .collect();
+    assert_eq!(
+        fields,
+        [
+            ("executable", "String", CommandFieldStatus::Required),
+            ("args", "Vec<String>", CommandFieldStatus::Each),
+            ("current_dir", "Option<String>", CommandFieldStatus::Optional),
+            ("timeout", "u64", CommandFieldStatus::Defaulted),
+            (
+                "env",
+                "std::collections::HashMap<&'static str, &'static str>",
+                CommandFieldStatus::Required,
+            ),
+            ("tls", "Tls", CommandFieldStatus::SubBuilder),
+        ]
+    );
+    let info: CommandFieldInfo = CommandBuilder::FIELDS[0];
+    assert_eq!(info.name, "executable");
+
+    let mut builder = Message::connect_builder();
+    builder.port(80);
+    assert_eq!(builder.missing_fields(), ["host"]);
+    assert_eq!(MessageConnectBuilder::FIELDS.len(), 2);
+    assert_eq!(MessageConnectBuilder::FIELDS[1].status, MessageFieldStatus::Required);
+
+    let mut builder = Request::builder();
+    assert_eq!(builder.missing_fields(), ["uri", "type"]);
+    builder.url("/".to_owned());
+    assert!(builder.is_set_url());
+    assert!(!builder.is_set_type());
+    assert_eq!(RequestBuilder::FIELDS[1].name, "type");
+}
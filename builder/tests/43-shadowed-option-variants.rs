@@ -0,0 +1,59 @@
+// Glob-importing an enum with variants named Some and None shadows the
+// prelude's in the value namespace, where type aliases like those of test 09
+// don't reach. The generated setters and build() still have to mean
+// Option's variants.
+
+use derive_builder::Builder;
+
+#[allow(dead_code)]
+pub enum Lookup {
+    Some(u32),
+    None,
+}
+
+#[allow(unused_imports)]
+use Lookup::*;
+
+#[derive(Builder)]
+pub struct Tls {
+    cert: String,
+}
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    current_dir: std::option::Option<String>,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    #[builder(each = "feature")]
+    features: std::option::Option<Vec<String>>,
+    #[builder(default = "30")]
+    timeout: u64,
+    #[builder(sub_builder)]
+    tls: Tls,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Job {
+    name: String,
+    label: std::option::Option<String>,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .current_dir("/".to_owned())
+        .arg("build".to_owned())
+        .feature("std".to_owned())
+        .tls(Tls::builder().cert("a.pem".to_owned()).build().unwrap())
+        .build()
+        .unwrap();
+    assert_eq!(command.executable, "cargo");
+    assert!(command.current_dir.is_some());
+    assert_eq!(command.timeout, 30);
+
+    let job = Job::builder().name("test".to_owned()).build();
+    assert!(job.label.is_none());
+    let _ = Lookup::Some(0);
+}
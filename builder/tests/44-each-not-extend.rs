@@ -0,0 +1,15 @@
+// `each` is accepted on any type with a type argument, as collections from
+// other crates work too, but the type then has to implement Default and
+// Extend of its items. A type that doesn't is reported at the field's type
+// rather than at the derive.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "byte")]
+    bytes: Box<u8>,
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+// A mutable outer builder's build() only borrows its sub-builders, so an
+// owned inner builder, whose build() consumes it, has to be Clone to be built
+// from there. If it isn't, the error points at the field.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug)]
+#[builder(pattern = "owned")]
+pub struct Retry {
+    attempts: u32,
+}
+
+#[derive(Builder, Debug)]
+pub struct Client {
+    #[builder(sub_builder)]
+    retry: Retry,
+}
+
+fn main() {}
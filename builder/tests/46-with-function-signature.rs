@@ -0,0 +1,17 @@
+// A function given to `with` has to come with its signature, as the macro
+// can't look it up to find the setter's parameters.
+
+use derive_builder::Builder;
+use std::num::ParseIntError;
+
+fn parse_port(port: &str) -> Result<u16, ParseIntError> {
+    port.parse()
+}
+
+#[derive(Builder)]
+pub struct Server {
+    #[builder(setter(with = "parse_port"))]
+    port: u16,
+}
+
+fn main() {}
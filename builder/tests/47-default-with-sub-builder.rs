@@ -0,0 +1,19 @@
+// A mutable builder can't copy its sub-builders for default_with closures to
+// read, so reading one is rejected at the closure.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Limits {
+    connections: u32,
+}
+
+#[derive(Builder)]
+pub struct Server {
+    #[builder(sub_builder)]
+    limits: Limits,
+    #[builder(default_with = "|b| b.limits.is_some()")]
+    limited: bool,
+}
+
+fn main() {}
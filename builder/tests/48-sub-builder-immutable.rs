@@ -0,0 +1,19 @@
+// An immutable builder is Clone, since each setter returns a modified copy,
+// so its sub-builders have to be Clone as well. If one isn't, the error points
+// at the field.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug)]
+pub struct Retry {
+    attempts: u32,
+}
+
+#[derive(Builder, Debug)]
+#[builder(pattern = "immutable")]
+pub struct Client {
+    #[builder(sub_builder)]
+    retry: Retry,
+}
+
+fn main() {}
@@ -10,4 +10,44 @@ fn tests() {
     t.pass("tests/07-repeated-field.rs");
     t.compile_fail("tests/08-unrecognized-attribute.rs");
     t.pass("tests/09-redefined-prelude-types.rs");
+    t.pass("tests/10-typestate.rs");
+    t.compile_fail("tests/11-typestate-missing-field.rs");
+    t.pass("tests/12-error-enum.rs");
+    t.pass("tests/13-default.rs");
+    t.pass("tests/14-generics.rs");
+    t.pass("tests/15-pattern.rs");
+    t.pass("tests/16-setter-into.rs");
+    t.pass("tests/17-validate.rs");
+    t.pass("tests/18-each-collections.rs");
+    t.pass("tests/19-enum.rs");
+    t.pass("tests/20-tuple-struct.rs");
+    t.compile_fail("tests/21-unknown-struct-option.rs");
+    t.compile_fail("tests/22-non-string-value.rs");
+    t.compile_fail("tests/23-each-non-collection.rs");
+    t.compile_fail("tests/24-union.rs");
+    t.compile_fail("tests/25-duplicate-option.rs");
+    t.compile_fail("tests/26-multiple-errors.rs");
+    t.pass("tests/27-visibility.rs");
+    t.pass("tests/28-skip.rs");
+    t.pass("tests/29-strip-option.rs");
+    t.pass("tests/30-from-value.rs");
+    #[cfg(feature = "serde")]
+    t.pass("tests/31-serde.rs");
+    t.pass("tests/32-no-std.rs");
+    t.pass("tests/33-custom-setter.rs");
+    t.pass("tests/34-sub-builder.rs");
+    t.pass("tests/35-docs.rs");
+    t.pass("tests/36-default-with.rs");
+    t.compile_fail("tests/37-default-with-cycle.rs");
+    t.pass("tests/38-field-groups.rs");
+    t.pass("tests/39-const-builder.rs");
+    t.compile_fail("tests/40-const-builder-heap.rs");
+    t.pass("tests/41-each-option-extend.rs");
+    t.pass("tests/42-introspection.rs");
+    t.pass("tests/43-shadowed-option-variants.rs");
+    t.compile_fail("tests/44-each-not-extend.rs");
+    t.compile_fail("tests/45-sub-builder-owned.rs");
+    t.compile_fail("tests/46-with-function-signature.rs");
+    t.compile_fail("tests/47-default-with-sub-builder.rs");
+    t.compile_fail("tests/48-sub-builder-immutable.rs");
 }